@@ -0,0 +1,175 @@
+//! Damas and Milner's Algorithm W, as a reference for the constraint-based
+//! inference in `infer_type`.
+//!
+//! It infers bottom-up, unifying as it goes and applying each substitution
+//! to the environment before moving on, which is simple enough to trust.
+//! Both must find the same principal type for every program, up to the
+//! names of type variables, and reject the same programs.
+
+use std::collections::HashSet;
+
+use crate::{
+    compose, fresh_type_var, generalize, instantiate, mgu, ApplySubst, Environment, Ir, Name,
+    Pattern, Substitutions, Type, TypeError,
+};
+
+/// the principal type of `expr` in `environment`, along with the
+/// substitution that was applied to the environment's free type variables
+/// # Errors
+/// Returns `TypeError::UnboundIdentifiers` for the first identifier that
+/// isn't in scope, or the error from `mgu` for the first types that can't be
+/// unified
+pub fn algorithm_w(
+    environment: &Environment,
+    expr: &Ir,
+) -> Result<(Substitutions, Type), TypeError> {
+    use Ir::*;
+    Ok(match expr {
+        Nop => (Substitutions::new(), Type::Nothing),
+        LiteralInt(_) => (Substitutions::new(), Type::Int),
+        LiteralStr(_) => (Substitutions::new(), Type::Str),
+        Id(name) => {
+            let (quantified, ty) = environment
+                .get(name)
+                .ok_or_else(|| TypeError::UnboundIdentifiers(vec![name]))?;
+            (
+                Substitutions::new(),
+                instantiate(quantified.iter().copied(), ty),
+            )
+        }
+        Hole(_) => (Substitutions::new(), fresh_type_var()),
+        App { e1, e2 } => {
+            let (s1, t1) = algorithm_w(environment, e1)?;
+            let (s2, t2) = algorithm_w(&substituted(environment, &s1), e2)?;
+            let result = fresh_type_var();
+            let s3 = mgu(
+                &substituted_type(&t1, &s2),
+                &Type::F(t2.into(), result.clone().into()),
+            )?;
+            let ty = substituted_type(&result, &s3);
+            (compose(s3, compose(s2, s1)), ty)
+        }
+        Lam { binding, body } => {
+            let param = fresh_type_var();
+            let mut environment = environment.clone();
+            environment.insert(binding, (HashSet::new(), param.clone()));
+            let (s1, t1) = algorithm_w(&environment, body)?;
+            let ty = Type::F(substituted_type(&param, &s1).into(), t1.into());
+            (s1, ty)
+        }
+        Let { e1, binding, e2 } => {
+            let (s1, t1) = algorithm_w(environment, e1)?;
+            let mut environment = substituted(environment, &s1);
+            let scheme = if is_value(e1) {
+                generalize(&environment, &t1)
+            } else {
+                (HashSet::new(), t1)
+            };
+            environment.insert(binding, scheme);
+            let (s2, t2) = algorithm_w(&environment, e2)?;
+            (compose(s2, s1), t2)
+        }
+        Add(lhs, rhs) => {
+            let (s1, t1) = algorithm_w(environment, lhs)?;
+            let s = compose(mgu(&t1, &Type::Int)?, s1);
+            let (s2, t2) = algorithm_w(&substituted(environment, &s), rhs)?;
+            let s = compose(s2, s);
+            (compose(mgu(&t2, &Type::Int)?, s), Type::Int)
+        }
+        Seq(lhs, rhs) => {
+            let (s1, _) = algorithm_w(environment, lhs)?;
+            let (s2, t2) = algorithm_w(&substituted(environment, &s1), rhs)?;
+            (compose(s2, s1), t2)
+        }
+        Annot { expr, ty } => {
+            let (s1, t1) = algorithm_w(environment, expr)?;
+            let s2 = mgu(&t1, ty)?;
+            let ty = substituted_type(ty, &s2);
+            (compose(s2, s1), ty)
+        }
+        Data { constructors, e2 } => {
+            let mut environment = environment.clone();
+            environment.extend(constructors.iter().cloned());
+            algorithm_w(&environment, e2)?
+        }
+        List(elements) => {
+            let element = fresh_type_var();
+            let mut s = Substitutions::new();
+            for e in elements {
+                let (s1, t1) = algorithm_w(&substituted(environment, &s), e)?;
+                s = compose(s1, s);
+                s = compose(mgu(&substituted_type(&element, &s), &t1)?, s);
+            }
+            let ty = Type::list(substituted_type(&element, &s));
+            (s, ty)
+        }
+        Match { scrutinee, arms } => {
+            let (mut s, scrutinee) = algorithm_w(environment, scrutinee)?;
+            let result = fresh_type_var();
+            for (pattern, arm) in arms {
+                let mut bindings = Vec::new();
+                let pattern = pattern_type(pattern, &mut bindings)?;
+                s = compose(mgu(&substituted_type(&scrutinee, &s), &pattern)?, s);
+                let mut environment = environment.clone();
+                environment
+                    .extend((bindings.into_iter()).map(|(name, ty)| (name, (HashSet::new(), ty))));
+                let (s1, t1) = algorithm_w(&substituted(&environment, &s), arm)?;
+                s = compose(s1, s);
+                s = compose(mgu(&substituted_type(&result, &s), &t1)?, s);
+            }
+            let ty = substituted_type(&result, &s);
+            (s, ty)
+        }
+        Located(_, inner) => algorithm_w(environment, inner)?,
+    })
+}
+
+/// the type of values `pattern` matches, adding the names it binds, with
+/// their types, to `bindings`
+fn pattern_type(pattern: &Pattern, bindings: &mut Vec<(Name, Type)>) -> Result<Type, TypeError> {
+    Ok(match pattern {
+        Pattern::Wildcard => fresh_type_var(),
+        Pattern::Var(name) => {
+            let ty = fresh_type_var();
+            bindings.push((name, ty.clone()));
+            ty
+        }
+        Pattern::Nil => Type::list(fresh_type_var()),
+        Pattern::Cons(head, tail) => {
+            let head = pattern_type(head, bindings)?;
+            let tail = pattern_type(tail, bindings)?;
+            let s = mgu(&tail, &Type::list(head))?;
+            for (_, ty) in bindings.iter_mut() {
+                ty.apply_subst(&s);
+            }
+            substituted_type(&tail, &s)
+        }
+    })
+}
+
+/// the value restriction, as `TypedIr::is_value` applies it
+fn is_value(expr: &Ir) -> bool {
+    use Ir::*;
+    match expr {
+        Nop | LiteralInt(_) | LiteralStr(_) | Id(_) | Hole(_) | Lam { .. } => true,
+        App { .. } | Add(..) | Seq(..) | Match { .. } => false,
+        List(elements) => elements.iter().all(is_value),
+        Let { e1, e2, .. } => is_value(e1) && is_value(e2),
+        Annot { expr: e, .. } | Located(_, e) | Data { e2: e, .. } => is_value(e),
+    }
+}
+
+/// `environment` with `s` applied to each scheme. Quantified variables are
+/// never in the domain of a substitution, since they're only ever
+/// instantiated
+fn substituted(environment: &Environment, s: &Substitutions) -> Environment {
+    (environment.iter())
+        .map(|(name, (quantified, ty))| (*name, (quantified.clone(), substituted_type(ty, s))))
+        .collect()
+}
+
+fn substituted_type(ty: &Type, s: &Substitutions) -> Type {
+    let mut ty = ty.clone();
+    ty.apply_subst(s);
+    ty
+}
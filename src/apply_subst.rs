@@ -32,8 +32,8 @@ impl ApplySubst for Type {
     fn apply_subst(&mut self, subs: &Substitutions) {
         use Type::*;
         match self {
-            Int | Str | Nothing => (),
-            F(t1, t2) => {
+            Int | Str | Nothing | Con(_) => (),
+            F(t1, t2) | App(t1, t2) => {
                 t1.apply_subst(subs);
                 t2.apply_subst(subs);
             }
@@ -42,6 +42,7 @@ impl ApplySubst for Type {
                     new.clone_into(self);
                 }
             }
+            Alias(_, expansion) => expansion.apply_subst(subs),
         }
     }
 }
@@ -1,29 +1,147 @@
-use crate::Ir;
+use crate::{
+    check_kind_star, fresh_type_id, hole::hole_text, infer_data_kind, infer_kind, Ir, Name,
+    Pattern, Span, Type, TypeError, TypeExpr, TypeScope,
+};
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Ast {
-    LiteralStr(&'static str),
+    LiteralStr(
+        #[cfg_attr(
+            feature = "serde",
+            serde(deserialize_with = "crate::serde_support::name")
+        )]
+        Name,
+    ),
     LiteralInt(i64),
-    Id(&'static str),
+    Id(
+        #[cfg_attr(
+            feature = "serde",
+            serde(deserialize_with = "crate::serde_support::name")
+        )]
+        Name,
+    ),
     App {
         e1: Box<Ast>,
         e2: Box<Ast>,
     },
+    /// `\x y -> body`, curried
     Lam {
-        binding: &'static str,
+        #[cfg_attr(
+            feature = "serde",
+            serde(deserialize_with = "crate::serde_support::names")
+        )]
+        bindings: Vec<Name>,
         body: Box<Ast>,
     },
+    /// `let binding = e1` statement, scoped over the rest of its `Do` block
     Let {
         e1: Box<Ast>,
-        binding: &'static str,
+        #[cfg_attr(
+            feature = "serde",
+            serde(deserialize_with = "crate::serde_support::name")
+        )]
+        binding: Name,
     },
+    /// `let x = e1, y = e2 in body`. Each binding is in scope for the ones
+    /// after it as well as the body
+    LetIn {
+        #[cfg_attr(
+            feature = "serde",
+            serde(deserialize_with = "crate::serde_support::named")
+        )]
+        bindings: Vec<(Name, Ast)>,
+        body: Box<Ast>,
+    },
+    /// `fn name x y = body`, curried
     Fn {
-        fn_name: &'static str,
-        parameter: &'static str,
+        #[cfg_attr(
+            feature = "serde",
+            serde(deserialize_with = "crate::serde_support::name")
+        )]
+        fn_name: Name,
+        #[cfg_attr(
+            feature = "serde",
+            serde(deserialize_with = "crate::serde_support::names")
+        )]
+        parameters: Vec<Name>,
         body: Box<Ast>,
     },
     Add(Box<Ast>, Box<Ast>),
     Do(Vec<Ast>),
+    /// `type name = ty`. The name may be used in annotations that follow it
+    TypeAlias {
+        #[cfg_attr(
+            feature = "serde",
+            serde(deserialize_with = "crate::serde_support::name")
+        )]
+        name: Name,
+        ty: TypeExpr,
+    },
+    /// `(expr : ty)`
+    Annot {
+        expr: Box<Ast>,
+        ty: TypeExpr,
+    },
+    /// `data name params = Constructor fields | ...`. The type and its
+    /// constructor functions may be used in the statements that follow it
+    Data {
+        #[cfg_attr(
+            feature = "serde",
+            serde(deserialize_with = "crate::serde_support::name")
+        )]
+        name: Name,
+        #[cfg_attr(
+            feature = "serde",
+            serde(deserialize_with = "crate::serde_support::names")
+        )]
+        params: Vec<Name>,
+        #[cfg_attr(
+            feature = "serde",
+            serde(deserialize_with = "crate::serde_support::named")
+        )]
+        constructors: Vec<(Name, Vec<TypeExpr>)>,
+    },
+    /// `import module (names)`. Makes the module's exports available as
+    /// `module.name`, and the listed ones unqualified as well
+    Import {
+        #[cfg_attr(
+            feature = "serde",
+            serde(deserialize_with = "crate::serde_support::name")
+        )]
+        module: Name,
+        #[cfg_attr(
+            feature = "serde",
+            serde(deserialize_with = "crate::serde_support::names")
+        )]
+        names: Vec<Name>,
+    },
+    /// `export names`. Limits which top-level bindings other modules can
+    /// import
+    Export(
+        #[cfg_attr(
+            feature = "serde",
+            serde(deserialize_with = "crate::serde_support::names")
+        )]
+        Vec<Name>,
+    ),
+    /// `[e1, e2, ...]`
+    List(Vec<Ast>),
+    /// `_` or `?name`, standing for an expression yet to be written
+    Hole(
+        #[cfg_attr(
+            feature = "serde",
+            serde(deserialize_with = "crate::serde_support::optional_name")
+        )]
+        Option<Name>,
+    ),
+    /// `match scrutinee { pattern -> arm ... }`
+    Match {
+        scrutinee: Box<Ast>,
+        arms: Vec<(Pattern, Ast)>,
+    },
+    /// where the wrapped node came from in the source text
+    Located(Span, Box<Ast>),
 }
 
 impl Ast {
@@ -33,9 +151,11 @@ impl Ast {
     fn display_tree_(&self, prefix: &str) {
         use Ast::*;
         match self {
+            Located(_, inner) => inner.display_tree_(prefix),
             LiteralStr(s) => println!("{prefix}+-\"{s}\""),
             LiteralInt(i) => println!("{prefix}+-{i}"),
             Id(s) => println!("{prefix}+-ID `{s}`"),
+            Hole(name) => println!("{prefix}+-HOLE {}", hole_text(*name)),
             App { e1, e2 } => {
                 println!("{prefix}+-App");
                 println!("{prefix}  |  |");
@@ -43,8 +163,8 @@ impl Ast {
                 println!("{prefix}  |");
                 e2.display_tree_(&format!("{prefix}  "));
             }
-            Lam { binding, body } => {
-                println!("{prefix}+-λ {binding} → ⋯");
+            Lam { bindings, body } => {
+                println!("{prefix}+-λ {} → ⋯", bindings.join(" "));
                 println!("{prefix}  |");
                 body.display_tree_(&format!("{prefix}  "));
             }
@@ -53,6 +173,19 @@ impl Ast {
                 println!("{prefix}  |");
                 e1.display_tree_(&format!("{prefix}  "));
             }
+            LetIn { bindings, body } => {
+                let names = bindings.iter().map(|(binding, _)| format!("{binding} = ⋯"));
+                println!(
+                    "{prefix}+-Let {} in ⋯",
+                    names.collect::<Vec<_>>().join(", ")
+                );
+                for (_, e1) in bindings {
+                    println!("{prefix}  |  |");
+                    e1.display_tree_(&format!("{prefix}  |  "));
+                }
+                println!("{prefix}  |");
+                body.display_tree_(&format!("{prefix}  "));
+            }
             Add(e1, e2) => {
                 println!("{prefix}+-Add");
                 println!("{prefix}  |  |");
@@ -62,10 +195,10 @@ impl Ast {
             }
             Fn {
                 fn_name,
-                parameter,
+                parameters,
                 body,
             } => {
-                println!("{prefix}+-Fn {fn_name} {parameter} = ⋯");
+                println!("{prefix}+-Fn {fn_name} {} = ⋯", parameters.join(" "));
                 println!("{prefix}  |");
                 body.display_tree_(&format!("{prefix}  "));
             }
@@ -76,79 +209,301 @@ impl Ast {
                     expr.display_tree_(&format!("{prefix}  |"));
                 }
             }
+            TypeAlias { name, ty } => println!("{prefix}+-Type {name} = {ty:?}"),
+            Import { module, names } => {
+                println!("{prefix}+-Import {module} ({})", names.join(", "));
+            }
+            Export(names) => println!("{prefix}+-Export {}", names.join(", ")),
+            Data {
+                name,
+                params,
+                constructors,
+            } => {
+                println!("{prefix}+-Data {name} {}", params.join(" "));
+                for (constructor, fields) in constructors {
+                    println!("{prefix}  +-{constructor} {fields:?}");
+                }
+            }
+            List(elements) => {
+                println!("{prefix}+-List");
+                for element in elements {
+                    println!("{prefix}  |");
+                    element.display_tree_(&format!("{prefix}  |"));
+                }
+            }
+            Match { scrutinee, arms } => {
+                println!("{prefix}+-Match");
+                println!("{prefix}  |  |");
+                scrutinee.display_tree_(&format!("{prefix}  |  "));
+                for (pattern, arm) in arms {
+                    println!("{prefix}  |");
+                    println!("{prefix}  +-{pattern} → ⋯");
+                    println!("{prefix}  |  |");
+                    arm.display_tree_(&format!("{prefix}  |  "));
+                }
+            }
+            Annot { expr, ty } => {
+                println!("{prefix}+-Annot ⋯ : {ty:?}");
+                println!("{prefix}  |");
+                expr.display_tree_(&format!("{prefix}  "));
+            }
         }
     }
 
-    #[must_use]
-    pub fn desugar(self) -> Ir {
-        use Ast::*;
+    /// # Errors
+    /// Returns `TypeError::UnknownType` if an annotation names a type that
+    /// isn't declared, `TypeError::DuplicateBinding` if a pattern, function
+    /// or data declaration binds the same name twice,
+    /// `TypeError::DuplicateType` if a data declaration names a data type
+    /// that's already in scope, or `TypeError::NotAtTopLevel` for an
+    /// `import` or `export`
+    pub fn desugar(self) -> Result<Ir, TypeError> {
+        self.desugar_(&TypeScope::new())
+    }
+    /// desugar a program made by `parse`. Unlike a `do` block, its last
+    /// statement isn't its value: it ends in `Nop` even after an
+    /// expression, so a trailing `let ... in` isn't taken for a top-level
+    /// declaration. Its `import` and `export` statements are left out,
+    /// since `ModuleLoader` resolves them before inference
+    /// # Errors
+    /// As for `desugar`, except that only an `import` or `export` that
+    /// isn't one of the program's statements is `TypeError::NotAtTopLevel`
+    pub fn desugar_program(self) -> Result<Ir, TypeError> {
+        self.without_module_statements()
+            .desugar_program_(&TypeScope::new())
+    }
+    fn desugar_program_(self, scope: &TypeScope) -> Result<Ir, TypeError> {
+        match self {
+            Ast::Located(span, inner) => {
+                Ok(Ir::Located(span, inner.desugar_program_(scope)?.into()))
+            }
+            Ast::Do(statements) => desugar_statements(statements.into_iter(), scope, true),
+            ast => ast.desugar_(scope),
+        }
+    }
+    /// the program without its top-level `import` and `export` statements,
+    /// which `ModuleLoader` resolves before inference
+    fn without_module_statements(self) -> Ast {
+        match self {
+            Ast::Located(span, inner) => {
+                Ast::Located(span, inner.without_module_statements().into())
+            }
+            Ast::Do(statements) => Ast::Do(
+                (statements.into_iter())
+                    .filter(|statement| {
+                        !matches!(statement.unlocated(), Ast::Import { .. } | Ast::Export(_))
+                    })
+                    .collect(),
+            ),
+            ast => ast,
+        }
+    }
+    fn unlocated(&self) -> &Ast {
         match self {
+            Ast::Located(_, inner) => inner.unlocated(),
+            ast => ast,
+        }
+    }
+    fn desugar_(self, scope: &TypeScope) -> Result<Ir, TypeError> {
+        use Ast::*;
+        Ok(match self {
             LiteralInt(x) => Ir::LiteralInt(x),
             LiteralStr(x) => Ir::LiteralStr(x),
             Id(x) => Ir::Id(x),
+            Hole(name) => Ir::Hole(name),
             App { e1, e2 } => Ir::App {
-                e1: (*e1).desugar().into(),
-                e2: (*e2).desugar().into(),
-            },
-            Lam { binding, body } => Ir::Lam {
-                binding,
-                body: (*body).desugar().into(),
-            },
-            Let { e1, binding } => Ir::Let {
-                e1: (*e1).desugar().into(),
-                binding,
-                e2: Ir::Nop.into(),
+                e1: e1.desugar_(scope)?.into(),
+                e2: e2.desugar_(scope)?.into(),
             },
-            Add(e1, e2) => Ir::Add((*e1).desugar().into(), (*e2).desugar().into()),
-            Fn {
-                fn_name,
-                parameter,
-                body,
-            } => Ir::Let {
-                e1: Ir::Lam {
-                    binding: parameter,
-                    body: (*body).desugar().into(),
+            Lam { bindings, body } => curry(bindings, body.desugar_(scope)?)?,
+            LetIn { bindings, body } => {
+                let body = body.desugar_(scope)?;
+                bindings
+                    .into_iter()
+                    .rev()
+                    .try_fold(body, |e2, (binding, e1)| {
+                        Ok(Ir::Let {
+                            e1: e1.desugar_(scope)?.into(),
+                            binding,
+                            e2: e2.into(),
+                        })
+                    })?
+            }
+            Add(e1, e2) => Ir::Add(e1.desugar_(scope)?.into(), e2.desugar_(scope)?.into()),
+            Annot { expr, ty } => {
+                check_kind_star(scope, &ty)?;
+                Ir::Annot {
+                    expr: expr.desugar_(scope)?.into(),
+                    ty: scope.resolve(&ty)?,
                 }
-                .into(),
-                binding: fn_name,
-                e2: Ir::Nop.into(),
+            }
+            Do(vec) => desugar_statements(vec.into_iter(), scope, false)?,
+            List(elements) => Ir::List(
+                elements
+                    .into_iter()
+                    .map(|element| element.desugar_(scope))
+                    .collect::<Result<_, _>>()?,
+            ),
+            Match { scrutinee, arms } => Ir::Match {
+                scrutinee: scrutinee.desugar_(scope)?.into(),
+                arms: arms
+                    .into_iter()
+                    .map(|(pattern, arm)| {
+                        if let Some(name) = repeated(&pattern.bound_names()) {
+                            return Err(TypeError::DuplicateBinding(name));
+                        }
+                        Ok((pattern, arm.desugar_(scope)?))
+                    })
+                    .collect::<Result<_, TypeError>>()?,
             },
-            Do(vec) => desugar_statements(vec.into_iter()),
+            Located(span, inner) => Ir::Located(span, inner.desugar_(scope)?.into()),
+            stmt @ (Let { .. }
+            | Fn { .. }
+            | TypeAlias { .. }
+            | Data { .. }
+            | Import { .. }
+            | Export(_)) => desugar_statements(std::iter::once(stmt), scope, false)?,
+        })
+    }
+
+    /// whether this is a statement that scopes over the ones after it
+    fn is_declaration(&self) -> bool {
+        use Ast::*;
+        match self {
+            Let { .. } | Fn { .. } | TypeAlias { .. } | Data { .. } | Import { .. } | Export(_) => {
+                true
+            }
+            Located(_, inner) => inner.is_declaration(),
+            _ => false,
         }
     }
 }
 
-/// helper for `Do` case of `Ast::desugar`
-fn desugar_statements<I>(stmts: I) -> Ir
+/// helper for `Do` case of `Ast::desugar`. Declarations scope over the
+/// statements that follow them. The last statement of a block is its
+/// value, unless the block is the whole `program`
+fn desugar_statements<I>(mut stmts: I, scope: &TypeScope, program: bool) -> Result<Ir, TypeError>
+where
+    I: Iterator<Item = Ast>,
+{
+    match stmts.next() {
+        Some(ast) => desugar_statement(ast, stmts, scope, program),
+        None => Ok(Ir::Nop),
+    }
+}
+
+/// desugar `ast`, followed by the rest of its statement block, `stmts`
+fn desugar_statement<I>(
+    ast: Ast,
+    stmts: I,
+    scope: &TypeScope,
+    program: bool,
+) -> Result<Ir, TypeError>
 where
-    I: Iterator<Item = Ast> + DoubleEndedIterator,
+    I: Iterator<Item = Ast>,
 {
-    stmts.rfold(Ir::Nop, |init, ast| match ast {
+    Ok(match ast {
+        // the location of a declaration ends up covering its scope as well
+        Ast::Located(span, stmt) if stmt.is_declaration() => Ir::Located(
+            span,
+            desugar_statement(*stmt, stmts, scope, program)?.into(),
+        ),
         Ast::Let { e1, binding } => Ir::Let {
-            e1: e1.desugar().into(),
+            e1: e1.desugar_(scope)?.into(),
             binding,
-            e2: init.into(),
+            e2: desugar_statements(stmts, scope, program)?.into(),
         },
         Ast::Fn {
             fn_name,
-            parameter,
+            parameters,
             body,
         } => Ir::Let {
-            e1: Ir::Lam {
-                binding: parameter,
-                body: (*body).desugar().into(),
-            }
-            .into(),
+            e1: curry(parameters, body.desugar_(scope)?)?.into(),
             binding: fn_name,
-            e2: init.into(),
+            e2: desugar_statements(stmts, scope, program)?.into(),
         },
+        // the top-level ones are gone by now
+        Ast::Import { .. } => return Err(TypeError::NotAtTopLevel("import")),
+        Ast::Export(_) => return Err(TypeError::NotAtTopLevel("export")),
+        Ast::TypeAlias { name, ty } => {
+            let mut scope = scope.clone();
+            let kind = infer_kind(&scope, &ty)?;
+            let expansion = scope.resolve(&ty)?;
+            scope.declare_alias(name, expansion, kind);
+            desugar_statements(stmts, &scope, program)?
+        }
+        Ast::Data {
+            name,
+            params,
+            constructors,
+        } => {
+            if scope.declares_data(name) {
+                return Err(TypeError::DuplicateType(name));
+            }
+            let constructor_names = (constructors.iter())
+                .map(|(constructor, _)| *constructor)
+                .collect::<Vec<_>>();
+            if let Some(name) = repeated(&params).or_else(|| repeated(&constructor_names)) {
+                return Err(TypeError::DuplicateBinding(name));
+            }
+            let (kind, param_kinds) = infer_data_kind(scope, name, &params, &constructors)?;
+            let mut scope = scope.clone();
+            scope.declare_data(name, kind);
+
+            // constructor types mention the parameters, which become the
+            // quantified variables of each constructor's scheme
+            let mut field_scope = scope.clone();
+            let mut quantified = Vec::new();
+            let mut result = Type::Con(name);
+            for (param, param_kind) in params.into_iter().zip(param_kinds) {
+                let id = fresh_type_id();
+                field_scope.declare_var(param, id, param_kind);
+                quantified.push(id);
+                result = Type::App(result.into(), Type::Unknown(id).into());
+            }
+            let constructors = constructors
+                .into_iter()
+                .map(|(constructor, fields)| {
+                    let ty = fields.iter().rev().try_fold(result.clone(), |ty, field| {
+                        Ok(Type::F(field_scope.resolve(field)?.into(), ty.into()))
+                    })?;
+                    Ok((constructor, (quantified.iter().copied().collect(), ty)))
+                })
+                .collect::<Result<_, TypeError>>()?;
+            Ir::Data {
+                constructors,
+                e2: desugar_statements(stmts, &scope, program)?.into(),
+            }
+        }
         _ => {
-            let ir = ast.desugar();
-            if matches!(init, Ir::Nop) {
+            let ir = ast.desugar_(scope)?;
+            let rest = desugar_statements(stmts, scope, program)?;
+            if matches!(rest, Ir::Nop) && !program {
                 ir
             } else {
-                Ir::Seq(ir.into(), init.into())
+                Ir::Seq(ir.into(), rest.into())
             }
         }
     })
 }
+
+/// the first name in `names` that's already come up before it
+fn repeated(names: &[Name]) -> Option<Name> {
+    (names.iter().enumerate())
+        .find(|(i, name)| names[..*i].contains(name))
+        .map(|(_, name)| *name)
+}
+
+/// nest single-parameter lambdas, so `\x y -> body` becomes `\x -> \y -> body`
+fn curry(bindings: Vec<&'static str>, body: Ir) -> Result<Ir, TypeError> {
+    if let Some(name) = repeated(&bindings) {
+        return Err(TypeError::DuplicateBinding(name));
+    }
+    Ok(bindings
+        .into_iter()
+        .rev()
+        .fold(body, |body, binding| Ir::Lam {
+            binding,
+            body: body.into(),
+        }))
+}
@@ -0,0 +1,47 @@
+#![warn(clippy::pedantic)]
+
+use std::process::ExitCode;
+
+use heeren_hage_swierstra::{default_environment, parse_environment, serve_language_server};
+
+const USAGE: &str = "usage: hhs_lsp [--prelude FILE]";
+
+/// language server for editors, speaking the protocol over stdin and stdout
+fn main() -> ExitCode {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let environment = match args.as_slice() {
+        [] => default_environment(),
+        [flag, path] if flag == "--prelude" => {
+            let parsed = std::fs::read_to_string(path)
+                .map_err(|e| format!("{path}: error: {e}"))
+                .and_then(|source| {
+                    parse_environment(&source).map_err(|e| {
+                        let (line, col) = e.span.line_col(&source);
+                        format!("{path}:{line}:{col}: error: {e}")
+                    })
+                });
+            match parsed {
+                Ok(environment) => environment,
+                Err(diagnostic) => {
+                    eprintln!("{diagnostic}");
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+        _ => {
+            eprintln!("{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    match serve_language_server(
+        std::io::stdin().lock(),
+        std::io::stdout().lock(),
+        environment,
+    ) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
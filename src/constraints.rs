@@ -1,15 +1,34 @@
-use std::{collections::HashSet, hash::Hash};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    hash::Hash,
+};
 
 use itertools::Itertools;
 
-use crate::{r#type::Scheme, ApplySubst, FreeVars, Substitutions, Type};
+use crate::{r#type::Scheme, ApplySubst, FreeVars, Span, Substitutions, Type};
 
 /// constraint set, organized by type
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone)]
 pub struct Constraints {
     equality: HashSet<(Type, Type)>,
     explicit: HashSet<ExplicitInstance>,
     implicit: HashSet<ImplicitInstance>,
+    /// the source expression each constraint was generated for, where known.
+    /// Keyed by the constraints as generated, before solving changes them
+    origins: HashMap<Constraint, Span>,
+    /// constraints inserted since the last call to `locate`
+    unlocated: Vec<Constraint>,
+    /// location of each identifier, by the type variable made for it
+    identifiers: HashMap<Type, Span>,
+}
+
+/// Any one constraint
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Constraint {
+    Equality(Type, Type),
+    Explicit(ExplicitInstance),
+    Implicit(ImplicitInstance),
 }
 
 #[derive(Clone, PartialEq, Eq, Debug)]
@@ -39,34 +58,106 @@ pub struct ImplicitInstance {
     /// `monomorphics`, will be made into quantified types, then the resulting
     /// scheme will be instantiated and unified with `instance`
     pub to_generalize: Type,
+    /// whether the bound expression is a syntactic value. Under the value
+    /// restriction, other expressions (which might allocate a `Ref`) are not
+    /// generalized at all
+    pub generalizable: bool,
 }
 
 impl Constraints {
+    /// add a constraint of any kind
+    pub fn insert(&mut self, constraint: Constraint) {
+        self.unlocated.push(constraint.clone());
+        self.add(constraint);
+    }
+    fn add(&mut self, constraint: Constraint) {
+        match constraint {
+            Constraint::Equality(left, right) => self.equality.insert((left, right)),
+            Constraint::Explicit(explicit) => self.explicit.insert(explicit),
+            Constraint::Implicit(implicit) => self.implicit.insert(implicit),
+        };
+    }
+    /// add a constraint about a use of an identifier, whose type is `ty`,
+    /// so it's blamed on that use rather than on whatever binds it
+    pub(crate) fn insert_for_identifier(&mut self, ty: &Type, constraint: Constraint) {
+        match self.identifiers.get(ty) {
+            Some(span) => {
+                self.origins.entry(constraint.clone()).or_insert(*span);
+                self.add(constraint);
+            }
+            None => self.insert(constraint),
+        }
+    }
+    /// blame the constraints inserted since the last call on the expression
+    /// at `span`
+    pub(crate) fn locate(&mut self, span: Span) {
+        for constraint in self.unlocated.drain(..) {
+            self.origins.entry(constraint).or_insert(span);
+        }
+    }
+    /// record that the identifier whose type is `ty` is at `span`
+    pub(crate) fn locate_identifier(&mut self, ty: &Type, span: Span) {
+        self.identifiers.entry(ty.clone()).or_insert(span);
+    }
+    /// move the locations constraints are blamed on, as after an edit to
+    /// the source text
+    pub(crate) fn relocate(&mut self, f: impl Fn(Span) -> Span) {
+        for span in (self.origins.values_mut()).chain(self.identifiers.values_mut()) {
+            *span = f(*span);
+        }
+    }
+    /// the source expression `constraint` was generated for, if known
+    #[must_use]
+    pub fn origin(&self, constraint: &Constraint) -> Option<Span> {
+        self.origins.get(constraint).copied()
+    }
+    /// every constraint, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = Constraint> + '_ {
+        (self.equality.iter())
+            .map(|(t1, t2)| Constraint::Equality(t1.clone(), t2.clone()))
+            .chain(self.explicit.iter().cloned().map(Constraint::Explicit))
+            .chain(self.implicit.iter().cloned().map(Constraint::Implicit))
+    }
     /// add an equality constraint
     pub fn insert_eq(&mut self, left: Type, right: Type) {
-        self.equality.insert((left, right));
+        self.insert(Constraint::Equality(left, right));
     }
     /// add an explicit instance constraint
     /// `instance` - the type that should be an instance of the scheme
     /// `scheme` - type scheme which we require instance to match
     pub fn insert_explicit(&mut self, instance: Type, scheme: (HashSet<u32>, Type)) {
-        self.explicit.insert(ExplicitInstance { instance, scheme });
+        self.insert(Constraint::Explicit(ExplicitInstance { instance, scheme }));
     }
     /// add an implicit instance constraint
     /// `instance` - the type that should be an instance of the yet-to-be-determined scheme
     /// `monomorphics` - types to not generalize when they appear in `to_generalize`
     /// `to_generalize` - type to be generalized into a scheme
+    /// `generalizable` - `false` if the value restriction forbids generalizing
     pub fn insert_implicit(
         &mut self,
         instance: Type,
         monomorphics: Box<[Type]>,
         to_generalize: Type,
+        generalizable: bool,
     ) {
-        self.implicit.insert(ImplicitInstance {
+        self.insert(Constraint::Implicit(ImplicitInstance {
             instance,
             monomorphics,
             to_generalize,
-        });
+            generalizable,
+        }));
+    }
+    /// the equality constraints, in no particular order
+    pub fn equalities(&self) -> impl Iterator<Item = &(Type, Type)> {
+        self.equality.iter()
+    }
+    /// the explicit instance constraints, in no particular order
+    pub fn explicit_instances(&self) -> impl Iterator<Item = &ExplicitInstance> {
+        self.explicit.iter()
+    }
+    /// the implicit instance constraints, in no particular order
+    pub fn implicit_instances(&self) -> impl Iterator<Item = &ImplicitInstance> {
+        self.implicit.iter()
     }
     /// Returns `true` if the constraint set contains no elements
     #[must_use]
@@ -78,6 +169,11 @@ impl Constraints {
         self.equality.extend(other.equality);
         self.explicit.extend(other.explicit);
         self.implicit.extend(other.implicit);
+        for (constraint, span) in other.origins {
+            self.origins.entry(constraint).or_insert(span);
+        }
+        self.unlocated.extend(other.unlocated);
+        self.identifiers.extend(other.identifiers);
     }
     /// create default  (empty) constraint set
     #[must_use]
@@ -94,14 +190,15 @@ impl Constraints {
     pub fn next_explicit(&self) -> Option<ExplicitInstance> {
         self.explicit.iter().next().cloned()
     }
-    /// Get an arbitrary one of the implicit instance constraints, if any.
+    /// Get an arbitrary one of the implicit instance constraints that is
+    /// ready to be solved, if any. It's ready once none of the type variables
+    /// it would generalize are still active elsewhere in the constraint set.
     #[must_use]
     pub fn next_implicit(&self) -> Option<ImplicitInstance> {
+        let active_vars = self.active_vars();
         self.implicit
             .iter()
-            .find(|ImplicitInstance { to_generalize, .. }| {
-                to_generalize.free_vars().is_disjoint(&self.active_vars())
-            })
+            .find(|imp| imp.generalized_vars().is_disjoint(&active_vars))
             .cloned()
     }
     /// remove an equality constraint
@@ -133,6 +230,17 @@ impl ApplySubst for ExplicitInstance {
     }
 }
 
+impl ImplicitInstance {
+    /// type variables that will become quantified when this is solved
+    fn generalized_vars(&self) -> HashSet<u32> {
+        if self.generalizable {
+            &self.to_generalize.free_vars() - &self.monomorphics.free_vars()
+        } else {
+            HashSet::new()
+        }
+    }
+}
+
 impl ApplySubst for ImplicitInstance {
     fn apply_subst(&mut self, subs: &Substitutions) {
         self.instance.apply_subst(subs);
@@ -160,6 +268,7 @@ impl ActiveVars for Constraints {
             instance,
             monomorphics,
             to_generalize,
+            ..
         } in &self.implicit
         {
             active_vars.extend(instance.free_vars().iter());
@@ -168,3 +277,67 @@ impl ActiveVars for Constraints {
         active_vars
     }
 }
+
+/// one constraint per line, in the paper's notation: `t1 ≡ t2` for
+/// equality, `t ≤ σ` for explicit instances and `t ≤_M t'` for implicit ones
+impl fmt::Display for Constraints {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // sets have no order of their own, so sort for stable output
+        let lines = (self.equality.iter())
+            .map(|(t1, t2)| format!("{t1} ≡ {t2}"))
+            .sorted()
+            .chain(self.explicit.iter().map(ToString::to_string).sorted())
+            .chain(self.implicit.iter().map(ToString::to_string).sorted());
+        write!(f, "{}", lines.format("\n"))
+    }
+}
+
+impl fmt::Display for Constraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Constraint::Equality(t1, t2) => write!(f, "{t1} ≡ {t2}"),
+            Constraint::Explicit(explicit) => write!(f, "{explicit}"),
+            Constraint::Implicit(implicit) => write!(f, "{implicit}"),
+        }
+    }
+}
+
+impl FromIterator<Constraint> for Constraints {
+    fn from_iter<I: IntoIterator<Item = Constraint>>(constraints: I) -> Self {
+        let mut set = Constraints::new();
+        for constraint in constraints {
+            set.insert(constraint);
+        }
+        set
+    }
+}
+
+impl fmt::Display for ExplicitInstance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (quantified, ty) = &self.scheme;
+        if quantified.is_empty() {
+            write!(f, "{} ≤ {ty}", self.instance)
+        } else {
+            let vars = quantified
+                .iter()
+                .sorted()
+                .format_with(" ", |id, f| f(&format_args!("t{id}")));
+            write!(f, "{} ≤ ∀{vars}. {ty}", self.instance)
+        }
+    }
+}
+
+impl fmt::Display for ImplicitInstance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let monomorphics = self.monomorphics.iter().format(", ");
+        write!(
+            f,
+            "{} ≤_{{{monomorphics}}} {}",
+            self.instance, self.to_generalize
+        )?;
+        if !self.generalizable {
+            write!(f, " (not generalized)")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,50 @@
+use std::fmt;
+
+use crate::{Span, Type, TypedIr};
+
+/// A statement in a `Do` block, other than the last, whose value is thrown away
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct DiscardedValue {
+    /// location of the statement, if known
+    pub span: Option<Span>,
+    /// type of the value that is discarded
+    pub ty: Type,
+}
+
+impl fmt::Display for DiscardedValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "discarded value of type {}", self.ty)?;
+        if let Some(span) = self.span {
+            write!(f, " at {span}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Opt-in check for non-final statements that don't have type `Nothing`.
+/// Run it on the `TypedIr` returned by `infer_type`, once substitutions have
+/// been applied. Statements whose type is still a type variable aren't
+/// reported, since they may yet be `Nothing`.
+#[must_use]
+pub fn check_discarded_values(typed_expr: &TypedIr) -> Vec<DiscardedValue> {
+    let mut discarded = Vec::new();
+    check_discarded_values_(typed_expr, &mut discarded);
+    discarded
+}
+
+fn check_discarded_values_(typed_expr: &TypedIr, discarded: &mut Vec<DiscardedValue>) {
+    // a program's last statement is followed by an empty one
+    if let TypedIr::Seq(stmt, rest) = typed_expr {
+        let ty = stmt.ty();
+        let last = matches!(**rest, TypedIr::Nop(_));
+        if !last && !matches!(ty.expand(), Type::Nothing | Type::Unknown(_)) {
+            discarded.push(DiscardedValue {
+                span: stmt.span(),
+                ty: ty.clone(),
+            });
+        }
+    }
+    for child in typed_expr.children() {
+        check_discarded_values_(child, discarded);
+    }
+}
@@ -0,0 +1,355 @@
+//! Graphviz output, for rendering trees and constraint sets with `dot`
+
+use std::{
+    collections::HashMap,
+    fmt::{Display, Write},
+};
+
+use itertools::Itertools;
+
+use crate::{hole::hole_text, Ast, Constraints, FreeVars, Ir, Type, TypedIr};
+
+/// a directed graph being written out in the DOT language
+struct DotGraph {
+    out: String,
+    nodes: usize,
+}
+
+impl DotGraph {
+    fn new(name: &str) -> Self {
+        Self {
+            out: format!("digraph {name} {{\n    node [fontname=monospace];\n"),
+            nodes: 0,
+        }
+    }
+    /// add a node, returning its id
+    fn node(&mut self, label: &str, attributes: &str) -> usize {
+        let id = self.nodes;
+        self.nodes += 1;
+        // writing to a String can't fail
+        let _ = writeln!(
+            self.out,
+            "    n{id} [label={}, {attributes}];",
+            quote(label)
+        );
+        id
+    }
+    fn edge(&mut self, from: usize, to: usize, label: &str, attributes: &str) {
+        let label = (!label.is_empty()).then(|| format!("label={}", quote(label)));
+        let attributes = label
+            .iter()
+            .map(String::as_str)
+            .chain((!attributes.is_empty()).then_some(attributes))
+            .join(", ");
+        let _ = writeln!(self.out, "    n{from} -> n{to} [{attributes}];");
+    }
+    fn finish(mut self) -> String {
+        self.out.push_str("}\n");
+        self.out
+    }
+}
+
+fn quote(label: &str) -> String {
+    let escaped = label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    format!("\"{escaped}\"")
+}
+
+/// a tree whose nodes become DOT nodes, and parent-child links edges
+trait DotTree {
+    fn label(&self) -> String;
+    /// children along with a label for the edge leading to each
+    fn children(&self) -> Vec<(String, &Self)>;
+    /// the node that stands in for this one in the graph, skipping wrappers
+    /// that only carry source locations
+    fn unwrap(&self) -> &Self;
+}
+
+fn tree_to_dot<T: DotTree>(name: &str, root: &T) -> String {
+    fn visit<T: DotTree>(graph: &mut DotGraph, node: &T) -> usize {
+        let node = node.unwrap();
+        let id = graph.node(&node.label(), "shape=box");
+        for (label, child) in node.children() {
+            let child = visit(graph, child);
+            graph.edge(id, child, &label, "");
+        }
+        id
+    }
+    let mut graph = DotGraph::new(name);
+    visit(&mut graph, root);
+    graph.finish()
+}
+
+fn unlabeled<'a, T>(children: impl IntoIterator<Item = &'a T>) -> Vec<(String, &'a T)>
+where
+    T: 'a,
+{
+    children.into_iter().map(|c| (String::new(), c)).collect()
+}
+
+impl Ast {
+    /// the syntax tree as a DOT graph
+    #[must_use]
+    pub fn to_dot(&self) -> String {
+        tree_to_dot("ast", self)
+    }
+}
+
+impl DotTree for Ast {
+    fn label(&self) -> String {
+        use Ast::*;
+        match self {
+            LiteralStr(s) => format!("{s:?}"),
+            LiteralInt(i) => i.to_string(),
+            Id(s) => (*s).to_string(),
+            Hole(name) => hole_text(*name),
+            App { .. } => "App".to_owned(),
+            Lam { bindings, .. } => format!("λ {}", bindings.join(" ")),
+            Let { binding, .. } => format!("let {binding}"),
+            LetIn { .. } => "let ... in".to_owned(),
+            Fn {
+                fn_name,
+                parameters,
+                ..
+            } => format!("fn {fn_name} {}", parameters.join(" ")),
+            Add(..) => "+".to_owned(),
+            Do(_) => "do".to_owned(),
+            TypeAlias { name, ty } => format!("type {name} = {ty}"),
+            Import { module, names } if names.is_empty() => format!("import {module}"),
+            Import { module, names } => format!("import {module} ({})", names.join(", ")),
+            Export(names) => format!("export {}", names.join(", ")),
+            Annot { ty, .. } => format!(": {ty}"),
+            Data {
+                name,
+                params,
+                constructors,
+            } => {
+                let constructors = constructors.iter().format_with(" | ", |(c, fields), f| {
+                    f(c)?;
+                    for field in fields {
+                        f(&format_args!(" {field}"))?;
+                    }
+                    Ok(())
+                });
+                let params = params
+                    .iter()
+                    .format_with("", |p, f| f(&format_args!(" {p}")));
+                format!("data {name}{params} = {constructors}")
+            }
+            List(_) => "[]".to_owned(),
+            Match { .. } => "match".to_owned(),
+            Located(_, inner) => inner.label(),
+        }
+    }
+    fn children(&self) -> Vec<(String, &Self)> {
+        use Ast::*;
+        match self {
+            LiteralStr(_)
+            | LiteralInt(_)
+            | Id(_)
+            | Hole(_)
+            | TypeAlias { .. }
+            | Data { .. }
+            | Import { .. }
+            | Export(_) => Vec::new(),
+            App { e1, e2 } | Add(e1, e2) => unlabeled([&**e1, e2]),
+            Lam { body: e1, .. } | Let { e1, .. } | Fn { body: e1, .. } => unlabeled([&**e1]),
+            Annot { expr, .. } => unlabeled([&**expr]),
+            LetIn { bindings, body } => bindings
+                .iter()
+                .map(|(name, e1)| ((*name).to_string(), e1))
+                .chain([("in".to_owned(), &**body)])
+                .collect(),
+            Do(elements) | List(elements) => unlabeled(elements),
+            Match { scrutinee, arms } => std::iter::once((String::new(), &**scrutinee))
+                .chain(arms.iter().map(|(pattern, arm)| (pattern.to_string(), arm)))
+                .collect(),
+            Located(_, inner) => inner.children(),
+        }
+    }
+    fn unwrap(&self) -> &Self {
+        match self {
+            Ast::Located(_, inner) => inner.unwrap(),
+            ast => ast,
+        }
+    }
+}
+
+impl Ir {
+    /// the intermediate representation as a DOT graph
+    #[must_use]
+    pub fn to_dot(&self) -> String {
+        tree_to_dot("ir", self)
+    }
+}
+
+impl DotTree for Ir {
+    fn label(&self) -> String {
+        use Ir::*;
+        match self {
+            Nop => "NOP".to_owned(),
+            LiteralStr(s) => format!("{s:?}"),
+            LiteralInt(i) => i.to_string(),
+            Id(s) => (*s).to_string(),
+            Hole(name) => hole_text(*name),
+            App { .. } => "App".to_owned(),
+            Lam { binding, .. } => format!("λ {binding}"),
+            Let { binding, .. } => format!("let {binding}"),
+            Add(..) => "+".to_owned(),
+            Seq(..) => "Seq".to_owned(),
+            Annot { ty, .. } => format!(": {ty}"),
+            Data { constructors, .. } => data_label(constructors.iter().map(|(c, _)| c)),
+            List(_) => "[]".to_owned(),
+            Match { .. } => "match".to_owned(),
+            Located(_, inner) => inner.label(),
+        }
+    }
+    fn children(&self) -> Vec<(String, &Self)> {
+        use Ir::*;
+        match self {
+            Nop | LiteralStr(_) | LiteralInt(_) | Id(_) | Hole(_) => Vec::new(),
+            App { e1, e2 } | Add(e1, e2) | Seq(e1, e2) => unlabeled([&**e1, e2]),
+            Let { e1, e2, .. } => vec![(String::new(), e1), ("in".to_owned(), e2)],
+            Lam { body: e, .. } | Annot { expr: e, .. } | Data { e2: e, .. } => unlabeled([&**e]),
+            List(elements) => unlabeled(elements),
+            Match { scrutinee, arms } => std::iter::once((String::new(), &**scrutinee))
+                .chain(arms.iter().map(|(pattern, arm)| (pattern.to_string(), arm)))
+                .collect(),
+            Located(_, inner) => inner.children(),
+        }
+    }
+    fn unwrap(&self) -> &Self {
+        match self {
+            Ir::Located(_, inner) => inner.unwrap(),
+            ir => ir,
+        }
+    }
+}
+
+impl TypedIr {
+    /// the typed tree as a DOT graph, with each node labeled by its type
+    #[must_use]
+    pub fn to_dot(&self) -> String {
+        tree_to_dot("typed", self)
+    }
+}
+
+impl DotTree for TypedIr {
+    fn label(&self) -> String {
+        use TypedIr::*;
+        let node = match self {
+            Nop(_) => "NOP".to_owned(),
+            LiteralStr(s, _) => format!("{s:?}"),
+            LiteralInt(i, _) => i.to_string(),
+            Id(s, _) => (*s).to_string(),
+            Hole(name, _) => hole_text(*name),
+            App { .. } => "App".to_owned(),
+            Lam { binding, .. } => format!("λ {binding}"),
+            Let { binding, .. } => format!("let {binding}"),
+            Add(..) => "+".to_owned(),
+            Seq(..) => "Seq".to_owned(),
+            Annot { .. } => "annotation".to_owned(),
+            Data { constructors, .. } => data_label(constructors.iter().map(|(c, _)| c)),
+            List(..) => "[]".to_owned(),
+            Match { .. } => "match".to_owned(),
+            Located(_, inner) => return inner.label(),
+        };
+        format!("{node}\n: {}", self.ty())
+    }
+    fn children(&self) -> Vec<(String, &Self)> {
+        use TypedIr::*;
+        match self {
+            Let { e1, e2, .. } => vec![(String::new(), e1), ("in".to_owned(), e2)],
+            Match {
+                scrutinee, arms, ..
+            } => std::iter::once((String::new(), &**scrutinee))
+                .chain(arms.iter().map(|(pattern, arm)| (pattern.to_string(), arm)))
+                .collect(),
+            _ => unlabeled(TypedIr::children(self)),
+        }
+    }
+    fn unwrap(&self) -> &Self {
+        match self {
+            TypedIr::Located(_, inner) => inner.unwrap(),
+            typed => typed,
+        }
+    }
+}
+
+fn data_label(constructors: impl Iterator<Item = impl Display>) -> String {
+    format!("data {}", constructors.format(" | "))
+}
+
+impl Constraints {
+    /// the constraint set as a DOT graph. Each type that appears in a
+    /// constraint is a node, with type variables drawn as ellipses.
+    /// Equality constraints are edges between their two sides, and instance
+    /// constraints are labeled edges from the instance. Dashed edges lead
+    /// from a type to the variables it mentions, so constraints that share a
+    /// variable are connected
+    #[must_use]
+    pub fn to_dot(&self) -> String {
+        let mut graph = ConstraintGraph {
+            graph: DotGraph::new("constraints"),
+            types: HashMap::new(),
+        };
+        for (t1, t2) in self
+            .equalities()
+            .sorted_by_key(|(t1, t2)| format!("{t1} ≡ {t2}"))
+        {
+            let n1 = graph.ty(t1);
+            let n2 = graph.ty(t2);
+            graph.graph.edge(n1, n2, "≡", "dir=none, color=blue");
+        }
+        for explicit in self.explicit_instances().sorted_by_key(ToString::to_string) {
+            let instance = graph.ty(&explicit.instance);
+            let (quantified, ty) = &explicit.scheme;
+            let vars = quantified
+                .iter()
+                .sorted()
+                .map(|id| format!("t{id}"))
+                .join(" ");
+            let scheme = graph.graph.node(&format!("∀{vars}. {ty}"), "shape=box");
+            graph.graph.edge(instance, scheme, "≤", "color=darkgreen");
+        }
+        for implicit in self.implicit_instances().sorted_by_key(ToString::to_string) {
+            let instance = graph.ty(&implicit.instance);
+            let to_generalize = graph.ty(&implicit.to_generalize);
+            let label = format!("≤_{{{}}}", implicit.monomorphics.iter().format(", "));
+            graph
+                .graph
+                .edge(instance, to_generalize, &label, "color=darkorange");
+        }
+        graph.graph.finish()
+    }
+}
+
+/// helper for `Constraints::to_dot`, which shares one node between every
+/// appearance of a type
+struct ConstraintGraph {
+    graph: DotGraph,
+    types: HashMap<Type, usize>,
+}
+
+impl ConstraintGraph {
+    fn ty(&mut self, ty: &Type) -> usize {
+        if let Some(id) = self.types.get(ty) {
+            return *id;
+        }
+        let id = if let Type::Unknown(_) = ty {
+            self.graph.node(&ty.to_string(), "shape=ellipse")
+        } else {
+            self.graph.node(&ty.to_string(), "shape=box")
+        };
+        self.types.insert(ty.clone(), id);
+        if !matches!(ty, Type::Unknown(_)) {
+            for var in ty.free_vars().into_iter().sorted() {
+                let var = self.ty(&Type::Unknown(var));
+                self.graph.edge(id, var, "", "style=dashed, arrowhead=none");
+            }
+        }
+        id
+    }
+}
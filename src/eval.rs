@@ -0,0 +1,395 @@
+use std::{cell::RefCell, collections::HashMap, fmt, io::Write, rc::Rc};
+
+use crate::{hole::hole_text, Ir, Pattern, Type};
+
+/// Runtime counterpart of `Environment`: values for the free identifiers of a program
+pub type RuntimeEnvironment<'ir> = HashMap<&'static str, Value<'ir>>;
+
+/// signature of built-in functions. They get all their arguments at once,
+/// after the value has been applied `arity` times
+pub type NativeFn = for<'ir> fn(&mut Interpreter<'_>, &[Value<'ir>]) -> EvalResult<'ir>;
+
+pub type EvalResult<'ir> = Result<Value<'ir>, EvalError>;
+
+/// Result of evaluating an expression. Closures borrow their body from the
+/// `Ir` being evaluated.
+#[derive(Clone)]
+pub enum Value<'ir> {
+    Int(i64),
+    Str(&'static str),
+    Bool(bool),
+    Nothing,
+    Nil,
+    Cons(Rc<(Value<'ir>, Value<'ir>)>),
+    Ref(Rc<RefCell<Value<'ir>>>),
+    Closure {
+        binding: &'static str,
+        body: &'ir Ir,
+        env: RuntimeEnvironment<'ir>,
+    },
+    Native {
+        name: &'static str,
+        arity: usize,
+        args: Vec<Value<'ir>>,
+        f: NativeFn,
+    },
+    /// value built by a data constructor. Until it has been given `arity`
+    /// fields, it is a function awaiting the rest
+    Data {
+        constructor: &'static str,
+        arity: usize,
+        fields: Vec<Value<'ir>>,
+    },
+}
+
+impl fmt::Display for Value<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Value::*;
+        match self {
+            Int(i) => write!(f, "{i}"),
+            Str(s) => write!(f, "{s:?}"),
+            Bool(b) => write!(f, "{b}"),
+            Nothing => write!(f, "nothing"),
+            Nil | Cons(_) => {
+                write!(f, "[")?;
+                let mut list = self;
+                while let Cons(cell) = list {
+                    write!(f, "{}", cell.0)?;
+                    list = &cell.1;
+                    if let Cons(_) = list {
+                        write!(f, ", ")?;
+                    }
+                }
+                write!(f, "]")
+            }
+            Ref(cell) => write!(f, "ref ({})", cell.borrow()),
+            Closure { .. } => write!(f, "<function>"),
+            Native { name, .. } => write!(f, "<built-in {name}>"),
+            Data {
+                constructor,
+                arity,
+                fields,
+            } if fields.len() == *arity => {
+                write!(f, "{constructor}")?;
+                for field in fields {
+                    write!(f, " ({field})")?;
+                }
+                Ok(())
+            }
+            Data { constructor, .. } => write!(f, "<constructor {constructor}>"),
+        }
+    }
+}
+
+/// Ways evaluation can fail
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum EvalError {
+    /// an identifier had no value in the runtime environment
+    Unbound(&'static str),
+    /// no evaluation rule applies, like adding a string or applying an
+    /// integer. Well-typed programs never get stuck.
+    Stuck(String),
+    /// no arm of a `match` accepted the value
+    NoMatch(String),
+    /// evaluation reached a hole, which has no value
+    Hole(Option<&'static str>),
+    /// `print` failed to write its output
+    Io(String),
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use EvalError::*;
+        match self {
+            Unbound(name) => write!(f, "no value for identifier `{name}`"),
+            Stuck(reason) => write!(f, "evaluation is stuck: {reason}"),
+            NoMatch(value) => write!(f, "no match arm accepts {value}"),
+            Hole(name) => write!(f, "reached hole `{}`", hole_text(*name)),
+            Io(err) => write!(f, "unable to print: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for EvalError {}
+
+/// Tree-walking evaluator. `print` writes to `out`.
+pub struct Interpreter<'o> {
+    out: &'o mut dyn Write,
+}
+
+impl<'o> Interpreter<'o> {
+    pub fn new(out: &'o mut dyn Write) -> Self {
+        Self { out }
+    }
+
+    /// evaluate `expr`, whose free identifiers are bound by `env`
+    /// # Errors
+    /// Returns an `EvalError` if an identifier isn't bound, evaluation gets
+    /// stuck, or no `match` arm applies
+    pub fn eval<'ir>(&mut self, env: &RuntimeEnvironment<'ir>, expr: &'ir Ir) -> EvalResult<'ir> {
+        use Ir::*;
+        match expr {
+            Nop => Ok(Value::Nothing),
+            LiteralInt(i) => Ok(Value::Int(*i)),
+            LiteralStr(s) => Ok(Value::Str(s)),
+            Id(name) => env.get(name).cloned().ok_or(EvalError::Unbound(name)),
+            Hole(name) => Err(EvalError::Hole(*name)),
+            App { e1, e2 } => {
+                let f = self.eval(env, e1)?;
+                let arg = self.eval(env, e2)?;
+                self.apply(f, arg)
+            }
+            Lam { binding, body } => Ok(Value::Closure {
+                binding,
+                body,
+                env: env.clone(),
+            }),
+            Let { e1, binding, e2 } => {
+                let value = self.eval(env, e1)?;
+                let mut env = env.clone();
+                env.insert(binding, value);
+                self.eval(&env, e2)
+            }
+            Add(lhs, rhs) => match (self.eval(env, lhs)?, self.eval(env, rhs)?) {
+                (Value::Int(lhs), Value::Int(rhs)) => Ok(Value::Int(lhs.wrapping_add(rhs))),
+                (lhs, rhs) => Err(EvalError::Stuck(format!("cannot add {lhs} and {rhs}"))),
+            },
+            Seq(lhs, rhs) => {
+                self.eval(env, lhs)?;
+                self.eval(env, rhs)
+            }
+            Annot { expr, .. } | Located(_, expr) => self.eval(env, expr),
+            Data { constructors, e2 } => {
+                let mut env = env.clone();
+                for (constructor, (_, ty)) in constructors {
+                    let value = Value::Data {
+                        constructor,
+                        arity: arity(ty),
+                        fields: Vec::new(),
+                    };
+                    env.insert(constructor, value);
+                }
+                self.eval(&env, e2)
+            }
+            List(elements) => {
+                let mut list = Value::Nil;
+                for element in elements.iter().rev() {
+                    let head = self.eval(env, element)?;
+                    list = Value::Cons((head, list).into());
+                }
+                Ok(list)
+            }
+            Match { scrutinee, arms } => {
+                let value = self.eval(env, scrutinee)?;
+                for (pattern, arm) in arms {
+                    let mut env = env.clone();
+                    if match_pattern(pattern, &value, &mut env)? {
+                        return self.eval(&env, arm);
+                    }
+                }
+                Err(EvalError::NoMatch(value.to_string()))
+            }
+        }
+    }
+
+    /// call a function value with an argument
+    /// # Errors
+    /// Returns `EvalError::Stuck` if `f` isn't a function, or any error
+    /// from evaluating its body
+    pub fn apply<'ir>(&mut self, f: Value<'ir>, arg: Value<'ir>) -> EvalResult<'ir> {
+        match f {
+            Value::Closure {
+                binding,
+                body,
+                mut env,
+            } => {
+                env.insert(binding, arg);
+                self.eval(&env, body)
+            }
+            Value::Native {
+                name,
+                arity,
+                mut args,
+                f,
+            } => {
+                args.push(arg);
+                if args.len() == arity {
+                    f(self, &args)
+                } else {
+                    Ok(Value::Native {
+                        name,
+                        arity,
+                        args,
+                        f,
+                    })
+                }
+            }
+            Value::Data {
+                constructor,
+                arity,
+                mut fields,
+            } if fields.len() < arity => {
+                fields.push(arg);
+                Ok(Value::Data {
+                    constructor,
+                    arity,
+                    fields,
+                })
+            }
+            other => Err(EvalError::Stuck(format!("cannot apply {other}"))),
+        }
+    }
+}
+
+/// number of fields a constructor with the given type takes
+fn arity(ty: &Type) -> usize {
+    match ty {
+        Type::F(_, result) => 1 + arity(result),
+        _ => 0,
+    }
+}
+
+/// if `value` matches `pattern`, bind the pattern's names in `env` and return `true`
+fn match_pattern<'ir>(
+    pattern: &Pattern,
+    value: &Value<'ir>,
+    env: &mut RuntimeEnvironment<'ir>,
+) -> Result<bool, EvalError> {
+    match (pattern, value) {
+        (Pattern::Wildcard, _) | (Pattern::Nil, Value::Nil) => Ok(true),
+        (Pattern::Var(name), value) => {
+            env.insert(name, value.clone());
+            Ok(true)
+        }
+        (Pattern::Cons(head, tail), Value::Cons(cell)) => {
+            Ok(match_pattern(head, &cell.0, env)? && match_pattern(tail, &cell.1, env)?)
+        }
+        (Pattern::Nil | Pattern::Cons(..), Value::Nil | Value::Cons(_)) => Ok(false),
+        (_, value) => Err(EvalError::Stuck(format!(
+            "cannot match {value} against {pattern}"
+        ))),
+    }
+}
+
+/// Values for everything in `default_environment`
+#[must_use]
+pub fn default_runtime_environment<'ir>() -> RuntimeEnvironment<'ir> {
+    let natives: [(&'static str, usize, NativeFn); 9] = [
+        ("print", 1, print),
+        ("len", 1, len),
+        ("ref", 1, new_ref),
+        ("!", 1, deref),
+        (":=", 2, assign),
+        ("map", 2, map),
+        ("filter", 2, filter),
+        ("fold", 3, fold),
+        ("length", 1, length),
+    ];
+    natives
+        .into_iter()
+        .map(|(name, arity, f)| {
+            let native = Value::Native {
+                name,
+                arity,
+                args: Vec::new(),
+                f,
+            };
+            (name, native)
+        })
+        .chain([("true", Value::Bool(true)), ("false", Value::Bool(false))])
+        .collect()
+}
+
+fn print<'ir>(interpreter: &mut Interpreter<'_>, args: &[Value<'ir>]) -> EvalResult<'ir> {
+    let result = match &args[0] {
+        Value::Str(s) => writeln!(interpreter.out, "{s}"),
+        value => writeln!(interpreter.out, "{value}"),
+    };
+    result.map_err(|err| EvalError::Io(err.to_string()))?;
+    Ok(Value::Nothing)
+}
+
+fn len<'ir>(_: &mut Interpreter<'_>, args: &[Value<'ir>]) -> EvalResult<'ir> {
+    match &args[0] {
+        Value::Str(s) => Ok(Value::Int(s.chars().count().try_into().unwrap_or(i64::MAX))),
+        other => Err(EvalError::Stuck(format!("len of {other}"))),
+    }
+}
+
+#[allow(clippy::unnecessary_wraps)] // signature is fixed by `NativeFn`
+fn new_ref<'ir>(_: &mut Interpreter<'_>, args: &[Value<'ir>]) -> EvalResult<'ir> {
+    Ok(Value::Ref(RefCell::new(args[0].clone()).into()))
+}
+
+fn deref<'ir>(_: &mut Interpreter<'_>, args: &[Value<'ir>]) -> EvalResult<'ir> {
+    match &args[0] {
+        Value::Ref(cell) => Ok(cell.borrow().clone()),
+        other => Err(EvalError::Stuck(format!("dereferencing {other}"))),
+    }
+}
+
+fn assign<'ir>(_: &mut Interpreter<'_>, args: &[Value<'ir>]) -> EvalResult<'ir> {
+    match &args[0] {
+        Value::Ref(cell) => {
+            *cell.borrow_mut() = args[1].clone();
+            Ok(Value::Nothing)
+        }
+        other => Err(EvalError::Stuck(format!("assigning to {other}"))),
+    }
+}
+
+/// elements of a list value, front to back
+fn elements<'ir>(mut list: &Value<'ir>) -> Result<Vec<Value<'ir>>, EvalError> {
+    let mut elements = Vec::new();
+    loop {
+        match list {
+            Value::Nil => return Ok(elements),
+            Value::Cons(cell) => {
+                elements.push(cell.0.clone());
+                list = &cell.1;
+            }
+            other => return Err(EvalError::Stuck(format!("expected a list, found {other}"))),
+        }
+    }
+}
+
+fn from_elements(elements: Vec<Value<'_>>) -> Value<'_> {
+    elements
+        .into_iter()
+        .rev()
+        .fold(Value::Nil, |list, head| Value::Cons((head, list).into()))
+}
+
+fn map<'ir>(interpreter: &mut Interpreter<'_>, args: &[Value<'ir>]) -> EvalResult<'ir> {
+    let mapped = elements(&args[1])?
+        .into_iter()
+        .map(|element| interpreter.apply(args[0].clone(), element))
+        .collect::<Result<_, _>>()?;
+    Ok(from_elements(mapped))
+}
+
+fn filter<'ir>(interpreter: &mut Interpreter<'_>, args: &[Value<'ir>]) -> EvalResult<'ir> {
+    let mut kept = Vec::new();
+    for element in elements(&args[1])? {
+        match interpreter.apply(args[0].clone(), element.clone())? {
+            Value::Bool(true) => kept.push(element),
+            Value::Bool(false) => (),
+            other => return Err(EvalError::Stuck(format!("filtering on {other}"))),
+        }
+    }
+    Ok(from_elements(kept))
+}
+
+fn fold<'ir>(interpreter: &mut Interpreter<'_>, args: &[Value<'ir>]) -> EvalResult<'ir> {
+    let mut acc = args[1].clone();
+    for element in elements(&args[2])? {
+        let partial = interpreter.apply(args[0].clone(), acc)?;
+        acc = interpreter.apply(partial, element)?;
+    }
+    Ok(acc)
+}
+
+fn length<'ir>(_: &mut Interpreter<'_>, args: &[Value<'ir>]) -> EvalResult<'ir> {
+    let count = elements(&args[0])?.len();
+    Ok(Value::Int(count.try_into().unwrap_or(i64::MAX)))
+}
@@ -1,4 +1,6 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+
+use crate::Scheme;
 
 pub trait FreeVars {
     fn free_vars(&self) -> HashSet<u32>;
@@ -10,8 +12,19 @@ where
 {
     fn free_vars(&self) -> HashSet<u32> {
         let mut free_vars = HashSet::new();
-        for elem in self.iter() {
-            free_vars.extend(elem.free_vars().into_iter());
+        for elem in self {
+            free_vars.extend(elem.free_vars());
+        }
+        free_vars
+    }
+}
+
+/// the type variables an environment's schemes don't quantify
+impl<K, S> FreeVars for HashMap<K, Scheme, S> {
+    fn free_vars(&self) -> HashSet<u32> {
+        let mut free_vars = HashSet::new();
+        for (quantified, ty) in self.values() {
+            free_vars.extend(ty.free_vars().difference(quantified));
         }
         free_vars
     }
@@ -0,0 +1,169 @@
+//! Typed holes: `_` or `?name` in place of an expression that hasn't been
+//! written yet.
+//!
+//! A hole can have any type, so inference goes ahead as if it were filled
+//! in. Once the constraints are solved, the type the rest of the program
+//! requires of it is known, and so are the bindings in scope there whose
+//! types fit.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt,
+};
+
+use crate::{
+    instantiate, intern, mgu, scheme_to_string, ApplySubst, Environment, FreeVars, Name, Pattern,
+    Scheme, Span, Substitutions, Type, TypedIr,
+};
+
+/// A hole in a typed program
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hole {
+    /// `None` for `_`
+    pub name: Option<Name>,
+    pub span: Option<Span>,
+    /// the type an expression must have to fill the hole
+    pub ty: Type,
+    /// bindings in scope at the hole that could fill it as they are, sorted
+    /// by name
+    pub fits: Vec<(Name, Scheme)>,
+}
+
+impl TypedIr {
+    /// the holes in a program whose type variables have been substituted,
+    /// in source order. `environment` is what the program was inferred in
+    #[must_use]
+    pub fn holes(&self, environment: &Environment) -> Vec<Hole> {
+        let mut holes = Vec::new();
+        collect(self, None, environment, &mut Vec::new(), &mut holes);
+        holes
+    }
+}
+
+/// `_` for an unnamed hole, `?name` for a named one
+pub(crate) fn hole_text(name: Option<Name>) -> String {
+    name.map_or_else(|| "_".to_owned(), |name| format!("?{name}"))
+}
+
+/// - `span` - location of `node`, if it's known
+/// - `scope` - schemes of the names bound around `node`, innermost last
+fn collect(
+    node: &TypedIr,
+    span: Option<Span>,
+    environment: &Environment,
+    scope: &mut Vec<(Name, Scheme)>,
+    holes: &mut Vec<Hole>,
+) {
+    use TypedIr::*;
+    match node {
+        Located(span, inner) => collect(inner, Some(*span), environment, scope, holes),
+        Hole(name, ty) => holes.push(self::Hole {
+            name: *name,
+            span,
+            ty: ty.clone(),
+            fits: fits(ty, environment, scope),
+        }),
+        Lam { binding, body, ty } => {
+            let Type::F(param, _) = ty else {
+                unreachable!("lambdas have function types")
+            };
+            scope.push((binding, (HashSet::new(), (**param).clone())));
+            collect(body, None, environment, scope, holes);
+            scope.pop();
+        }
+        Let {
+            e1, binding, e2, ..
+        } => {
+            collect(e1, None, environment, scope, holes);
+            let ty = e1.ty().clone();
+            let mut quantified = HashSet::new();
+            if e1.is_value() {
+                let monomorphic = (environment.values().chain(scope.iter().map(|(_, s)| s)))
+                    .flat_map(|(bound, ty)| &ty.free_vars() - bound)
+                    .collect::<HashSet<_>>();
+                quantified = &ty.free_vars() - &monomorphic;
+            }
+            scope.push((binding, (quantified, ty)));
+            collect(e2, None, environment, scope, holes);
+            scope.pop();
+        }
+        Data { constructors, e2 } => {
+            let depth = scope.len();
+            scope.extend(constructors.iter().cloned());
+            collect(e2, None, environment, scope, holes);
+            scope.truncate(depth);
+        }
+        Match {
+            scrutinee, arms, ..
+        } => {
+            collect(scrutinee, None, environment, scope, holes);
+            for (pattern, arm) in arms {
+                let depth = scope.len();
+                bind_pattern(pattern, scrutinee.ty(), scope);
+                collect(arm, None, environment, scope, holes);
+                scope.truncate(depth);
+            }
+        }
+        _ => {
+            for child in node.children() {
+                collect(child, None, environment, scope, holes);
+            }
+        }
+    }
+}
+
+/// add the names `pattern` binds to `scope`, given the type of the values
+/// it matches
+fn bind_pattern(pattern: &Pattern, ty: &Type, scope: &mut Vec<(Name, Scheme)>) {
+    match pattern {
+        Pattern::Wildcard | Pattern::Nil => (),
+        Pattern::Var(name) => scope.push((name, (HashSet::new(), ty.clone()))),
+        Pattern::Cons(head, tail) => {
+            if let Type::App(_, element) = ty.expand() {
+                bind_pattern(head, element, scope);
+            }
+            bind_pattern(tail, ty, scope);
+        }
+    }
+}
+
+/// the bindings visible in `scope` or `environment` whose schemes can be
+/// instantiated to `ty`. The type variables in `ty`, and the ones a
+/// binding's scheme doesn't quantify, stand for types the program hasn't
+/// decided yet, so a binding only fits if it would whatever they are
+fn fits(ty: &Type, environment: &Environment, scope: &[(Name, Scheme)]) -> Vec<(Name, Scheme)> {
+    let mut visible = environment
+        .iter()
+        .map(|(name, scheme)| (*name, scheme))
+        .collect::<BTreeMap<_, _>>();
+    // inner bindings shadow outer ones
+    visible.extend(scope.iter().map(|(name, scheme)| (*name, scheme)));
+    visible
+        .into_iter()
+        .filter(|(_, (quantified, candidate))| {
+            let rigid = (ty.free_vars().into_iter())
+                .chain(&candidate.free_vars() - quantified)
+                .map(|id| (id, Type::Con(intern(&format!("t{id}")))))
+                .collect::<Substitutions>();
+            let mut instance = instantiate(quantified.iter().copied(), candidate);
+            instance.apply_subst(&rigid);
+            let mut ty = ty.clone();
+            ty.apply_subst(&rigid);
+            mgu(&instance, &ty).is_ok()
+        })
+        .map(|(name, scheme)| (name, scheme.clone()))
+        .collect()
+}
+
+impl fmt::Display for Hole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "hole `{}` : {}", hole_text(self.name), self.ty)?;
+        if !self.fits.is_empty() {
+            write!(f, "\n  bindings that fit:")?;
+            for (name, scheme) in &self.fits {
+                write!(f, "\n    {name} : {}", scheme_to_string(scheme))?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,480 @@
+//! Incremental inference for a program that's edited and re-checked, as in
+//! an editor.
+//!
+//! Each top-level statement is inferred on its own, in the environment left
+//! by the ones before it. The result is cached, keyed by the statement's
+//! source text, the types its annotations resolve to and the schemes of the
+//! names it mentions. A statement is only inferred again when one of those
+//! changed, so re-inferring a binding that ends up with the same scheme
+//! doesn't ripple out to its dependents. When an edit elsewhere moves a
+//! statement, the locations in what was cached for it move along.
+//!
+//! A binding the value restriction keeps from being generalized may still
+//! have type variables that later statements decide, like the element type
+//! of `let r = ref []`. Statements that mention such a binding are inferred
+//! together with it, along with everything in between.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use crate::{
+    default_environment, generate_constraints, prelude::expand_aliases, scheme_to_string, solve,
+    ApplySubst, Constraints, Environment, FreeVars, Ir, Name, Scheme, Span, Type, TypeError,
+    TypedIr,
+};
+
+/// Remembers what was inferred for each top-level binding of a program
+/// across calls to `check`
+#[derive(Debug, Clone)]
+pub struct IncrementalInference {
+    environment: Environment,
+    cache: HashMap<Vec<String>, CachedUnit>,
+    /// which cache entry each binding of the last checked program came from
+    current: Vec<(Name, Vec<String>)>,
+}
+
+/// Result of `IncrementalInference::check`
+#[derive(Debug, Clone)]
+pub struct Checked {
+    /// schemes of the names bound by top-level statements, including data
+    /// constructors, in order
+    pub bindings: Vec<(Name, Scheme)>,
+    /// positions of the top-level statements that were inferred, rather
+    /// than taken from the cache
+    pub reinferred: Vec<usize>,
+    /// the whole program with its types
+    pub typed: TypedIr,
+}
+
+/// what was inferred for a run of top-level statements
+#[derive(Debug, Clone)]
+struct CachedUnit {
+    /// schemes of the names the statements mention, as they were when the
+    /// statements were inferred
+    dependencies: Vec<(Name, Option<String>)>,
+    bindings: Vec<(Name, Scheme)>,
+    constraints: Constraints,
+    typed: TypedIr,
+    /// where the statements were when they were inferred
+    spans: Vec<Option<Span>>,
+    /// whether a binding has type variables that aren't quantified
+    open: bool,
+}
+
+/// a run of top-level statements in the program being checked
+struct Unit {
+    /// position of the first statement
+    start: usize,
+    key: Vec<String>,
+    /// what's in scope for the first statement
+    environment: Environment,
+    bindings: Vec<(Name, Scheme)>,
+    typed: TypedIr,
+    open: bool,
+}
+
+/// a top-level statement. Declarations keep the location of their source
+/// text, which an expression has as its outermost node
+#[derive(Debug, Clone)]
+enum Statement {
+    Let {
+        binding: Name,
+        e1: Ir,
+        span: Option<Span>,
+    },
+    Data {
+        constructors: Vec<(Name, Scheme)>,
+        span: Option<Span>,
+    },
+    Expr(Ir),
+}
+
+impl Default for IncrementalInference {
+    fn default() -> Self {
+        Self::new(default_environment())
+    }
+}
+
+impl IncrementalInference {
+    /// start with nothing cached, checking programs in `environment`
+    #[must_use]
+    pub fn new(environment: Environment) -> Self {
+        Self {
+            environment,
+            cache: HashMap::new(),
+            current: Vec::new(),
+        }
+    }
+
+    /// the environment programs are checked in
+    #[must_use]
+    pub fn environment(&self) -> &Environment {
+        &self.environment
+    }
+
+    /// infer the top-level bindings of `program`, desugared from `source`,
+    /// reusing what was inferred for statements that haven't changed since
+    /// an earlier call
+    /// # Errors
+    /// Returns the `TypeError` of the first statement that doesn't
+    /// typecheck. What was inferred for the ones before it is still cached
+    pub fn check(&mut self, source: &str, program: Ir) -> Result<Checked, TypeError> {
+        let statements = statements(program);
+        let mut units: Vec<Unit> = Vec::new();
+        let mut environment = self.environment.clone();
+        let mut reinferred = BTreeSet::new();
+        let mut used = HashSet::new();
+        for (i, statement) in statements.iter().enumerate() {
+            // the earliest open unit binding a name this statement mentions
+            let open = free_identifiers(statement)
+                .iter()
+                .filter_map(|name| {
+                    (units.iter()).rposition(|unit| unit.bindings.iter().any(|(b, _)| b == name))
+                })
+                .filter(|&j| units[j].open)
+                .min();
+            let (start, environment_before) = match open.and_then(|j| units.drain(j..).next()) {
+                Some(first) => (first.start, first.environment),
+                None => (i, environment.clone()),
+            };
+            let (key, cached, inferred) =
+                self.infer(source, &statements[start..=i], &environment_before)?;
+            if inferred {
+                reinferred.extend(start..=i);
+            }
+            environment.clone_from(&environment_before);
+            environment.extend(cached.bindings.iter().cloned());
+            used.insert(key.clone());
+            units.push(Unit {
+                start,
+                key,
+                environment: environment_before,
+                bindings: cached.bindings,
+                typed: cached.typed,
+                open: cached.open,
+            });
+        }
+        self.cache.retain(|key, _| used.contains(key));
+        self.current = (units.iter())
+            .flat_map(|unit| {
+                unit.bindings
+                    .iter()
+                    .map(|(name, _)| (*name, unit.key.clone()))
+            })
+            .collect();
+        let mut bindings = Vec::new();
+        let mut typed = None;
+        for unit in units.into_iter().rev() {
+            bindings.splice(0..0, unit.bindings);
+            let mut program = unit.typed;
+            if let Some(rest) = typed {
+                append(&mut program, rest);
+            }
+            typed = Some(program);
+        }
+        Ok(Checked {
+            bindings,
+            reinferred: reinferred.into_iter().collect(),
+            typed: typed.unwrap_or(TypedIr::Nop(Type::Nothing)),
+        })
+    }
+
+    /// the constraints generated for the statement that bound `name` in the
+    /// last program checked, or for the run of statements inferred along
+    /// with it
+    #[must_use]
+    pub fn constraints(&self, name: &str) -> Option<&Constraints> {
+        let (_, key) = self
+            .current
+            .iter()
+            .rev()
+            .find(|(bound, _)| *bound == name)?;
+        self.cache.get(key).map(|cached| &cached.constraints)
+    }
+
+    /// what `statements` bind, from the cache if possible. Also returns the
+    /// cache key, and whether they had to be inferred
+    fn infer(
+        &mut self,
+        source: &str,
+        statements: &[Statement],
+        environment: &Environment,
+    ) -> Result<(Vec<String>, CachedUnit, bool), TypeError> {
+        let key = (statements.iter())
+            .map(|statement| statement.key(source))
+            .collect::<Vec<_>>();
+        let spans = statements.iter().map(Statement::span).collect::<Vec<_>>();
+        let dependencies = (statements.iter())
+            .flat_map(free_identifiers)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .map(|name| (name, environment.get(name).map(spelled_out)))
+            .collect::<Vec<_>>();
+        if let Some(cached) = self.cache.get_mut(&key) {
+            if cached.dependencies == dependencies {
+                if cached.spans != spans {
+                    cached.relocate(&spans);
+                }
+                return Ok((key, cached.clone(), false));
+            }
+        }
+        let (constraints, mut typed) = generate_constraints(environment, assemble(statements))?;
+        let substitution = solve(constraints.clone())?;
+        typed.apply_subst(&substitution);
+        let holes = typed.holes(environment);
+        if !holes.is_empty() {
+            return Err(TypeError::Holes(holes));
+        }
+        let bindings = typed.top_level_bindings(environment);
+        let open =
+            (bindings.iter()).any(|(_, (quantified, ty))| !ty.free_vars().is_subset(quantified));
+        let cached = CachedUnit {
+            dependencies,
+            bindings,
+            constraints,
+            typed,
+            spans,
+            open,
+        };
+        self.cache.insert(key.clone(), cached.clone());
+        Ok((key, cached, true))
+    }
+}
+
+impl CachedUnit {
+    /// move the locations in what was inferred to where the statements are
+    /// now. Their text hasn't changed, so everything in a statement moves by
+    /// as much as the statement did
+    fn relocate(&mut self, spans: &[Option<Span>]) {
+        let moves = (self.spans.iter().zip(spans))
+            .filter_map(|(old, new)| Some(((*old)?, (*new)?)))
+            .collect::<Vec<_>>();
+        let relocate = |span: Span| {
+            (moves.iter())
+                .find(|(old, _)| old.start <= span.start && span.end <= old.end)
+                .map_or(span, |(old, new)| {
+                    Span::new(
+                        span.start - old.start + new.start,
+                        span.end - old.start + new.start,
+                    )
+                })
+        };
+        self.constraints.relocate(relocate);
+        self.typed.relocate(&relocate);
+        self.spans = spans.to_vec();
+    }
+}
+
+impl Statement {
+    /// identifies the statement regardless of where it is in `source`: its
+    /// text, along with the types the type names in it stand for, which
+    /// declarations elsewhere decide
+    fn key(&self, source: &str) -> String {
+        let mut types = Vec::new();
+        match self {
+            Statement::Let { e1: e, .. } | Statement::Expr(e) => declared_types(e, &mut types),
+            Statement::Data { constructors, .. } => {
+                types.extend(constructors.iter().map(|(_, scheme)| spelled_out(scheme)));
+            }
+        }
+        let text = match self.span() {
+            Some(span) => &source[span.start..span.end],
+            // nothing else distinguishes statements without a location
+            None => &format!("{self:?}"),
+        };
+        format!("{text}\n{}", types.join(", "))
+    }
+
+    /// location of the statement's source text, if known
+    fn span(&self) -> Option<Span> {
+        match self {
+            Statement::Let { span, .. } | Statement::Data { span, .. } => *span,
+            Statement::Expr(e) => match e {
+                Ir::Located(span, _) => Some(*span),
+                _ => None,
+            },
+        }
+    }
+}
+
+/// split a desugared program into its top-level statements
+fn statements(program: Ir) -> Vec<Statement> {
+    let mut statements = Vec::new();
+    let mut node = program;
+    // location of the declaration `node` is
+    let mut span = None;
+    loop {
+        node = match node {
+            Ir::Located(located, inner)
+                if matches!(
+                    *inner,
+                    Ir::Let { .. } | Ir::Data { .. } | Ir::Seq(..) | Ir::Nop | Ir::Located(..)
+                ) =>
+            {
+                span = Some(located);
+                *inner
+            }
+            Ir::Let { e1, binding, e2 } => {
+                let span = span.take();
+                statements.push(Statement::Let {
+                    binding,
+                    e1: *e1,
+                    span,
+                });
+                *e2
+            }
+            Ir::Data { constructors, e2 } => {
+                let span = span.take();
+                statements.push(Statement::Data { constructors, span });
+                *e2
+            }
+            Ir::Seq(e, rest) => {
+                span = None;
+                statements.push(Statement::Expr(*e));
+                *rest
+            }
+            Ir::Nop => return statements,
+            e => {
+                statements.push(Statement::Expr(e));
+                return statements;
+            }
+        }
+    }
+}
+
+/// the inverse of `statements`
+fn assemble(statements: &[Statement]) -> Ir {
+    statements.iter().rev().fold(Ir::Nop, |rest, statement| {
+        let rest = rest.into();
+        let declaration = match statement {
+            Statement::Let { binding, e1, .. } => Ir::Let {
+                e1: e1.clone().into(),
+                binding,
+                e2: rest,
+            },
+            Statement::Data { constructors, .. } => Ir::Data {
+                constructors: constructors.clone(),
+                e2: rest,
+            },
+            // even the last one, so a `let ... in` expression doesn't look
+            // like a top-level binding
+            Statement::Expr(e) => return Ir::Seq(e.clone().into(), rest),
+        };
+        match statement.span() {
+            Some(span) => Ir::Located(span, declaration.into()),
+            None => declaration,
+        }
+    })
+}
+
+/// put `rest` in place of the empty statement `program` ends with
+fn append(program: &mut TypedIr, rest: TypedIr) {
+    match program {
+        TypedIr::Located(_, inner) | TypedIr::Data { e2: inner, .. } | TypedIr::Seq(_, inner) => {
+            append(inner, rest);
+        }
+        TypedIr::Let { e2, ty, .. } => {
+            append(e2, rest);
+            ty.clone_from(e2.ty());
+        }
+        nop => *nop = rest,
+    }
+}
+
+/// names a statement uses without binding them itself
+fn free_identifiers(statement: &Statement) -> BTreeSet<Name> {
+    let mut free = BTreeSet::new();
+    match statement {
+        Statement::Let { e1: e, .. } | Statement::Expr(e) => {
+            free_in(e, &mut Vec::new(), &mut free);
+        }
+        Statement::Data { .. } => (),
+    }
+    free
+}
+
+fn free_in(ir: &Ir, bound: &mut Vec<Name>, free: &mut BTreeSet<Name>) {
+    use Ir::*;
+    match ir {
+        Nop | LiteralStr(_) | LiteralInt(_) | Hole(_) => (),
+        Id(name) => {
+            if !bound.contains(name) {
+                free.insert(name);
+            }
+        }
+        App { e1, e2 } | Add(e1, e2) | Seq(e1, e2) => {
+            free_in(e1, bound, free);
+            free_in(e2, bound, free);
+        }
+        Lam { binding, body } => {
+            bound.push(binding);
+            free_in(body, bound, free);
+            bound.pop();
+        }
+        Let { e1, binding, e2 } => {
+            free_in(e1, bound, free);
+            bound.push(binding);
+            free_in(e2, bound, free);
+            bound.pop();
+        }
+        Data { constructors, e2 } => {
+            let depth = bound.len();
+            bound.extend(constructors.iter().map(|(name, _)| *name));
+            free_in(e2, bound, free);
+            bound.truncate(depth);
+        }
+        List(elements) => {
+            for element in elements {
+                free_in(element, bound, free);
+            }
+        }
+        Match { scrutinee, arms } => {
+            free_in(scrutinee, bound, free);
+            for (pattern, arm) in arms {
+                let depth = bound.len();
+                bound.extend(pattern.bound_names());
+                free_in(arm, bound, free);
+                bound.truncate(depth);
+            }
+        }
+        Annot { expr: e, .. } | Located(_, e) => free_in(e, bound, free),
+    }
+}
+
+/// the types of the annotations and data constructors in `ir`, with their
+/// type variables named in order
+fn declared_types(ir: &Ir, types: &mut Vec<String>) {
+    use Ir::*;
+    match ir {
+        Nop | LiteralStr(_) | LiteralInt(_) | Id(_) | Hole(_) => (),
+        App { e1, e2 } | Let { e1, e2, .. } | Add(e1, e2) | Seq(e1, e2) => {
+            declared_types(e1, types);
+            declared_types(e2, types);
+        }
+        Lam { body: e, .. } | Located(_, e) => declared_types(e, types),
+        Annot { expr, ty } => {
+            types.push(spelled_out(&(ty.free_vars(), ty.clone())));
+            declared_types(expr, types);
+        }
+        Data { constructors, e2 } => {
+            types.extend(constructors.iter().map(|(_, scheme)| spelled_out(scheme)));
+            declared_types(e2, types);
+        }
+        List(elements) => {
+            for element in elements {
+                declared_types(element, types);
+            }
+        }
+        Match { scrutinee, arms } => {
+            declared_types(scrutinee, types);
+            for (_, arm) in arms {
+                declared_types(arm, types);
+            }
+        }
+    }
+}
+
+/// `scheme` with its aliases expanded, since what an alias stands for can
+/// change while its name stays the same
+fn spelled_out((quantified, ty): &Scheme) -> String {
+    scheme_to_string(&(quantified.clone(), expand_aliases(ty)))
+}
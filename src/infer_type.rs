@@ -1,20 +1,48 @@
 use crate::{
-    fresh_type_var, solve, ApplySubst, Assumptions, Constraints, Environment, Ir, Substitutions,
-    Type, TypeSet, TypedIr,
+    fresh_type_var, solve, ApplySubst, Assumptions, Constraint, Constraints, Environment,
+    ExplicitInstance, FreeVars, ImplicitInstance, Ir, Pattern, Scheme, Span, Substitutions, Type,
+    TypeError, TypeSet, TypedIr,
 };
 use itertools::Itertools;
 
-/// # Panics
-/// If the expression referred to an identifier that could not be
-/// found in that scope, it panics with a message including those varnames
-#[must_use]
-pub fn infer_type(environment: &Environment, expr: Ir) -> (Substitutions, TypedIr) {
-    //let env_types = environment.iter().map(|(_, (_, t))| t.clone()).collect();
+/// # Errors
+/// Returns `TypeError::UnboundIdentifiers` if the expression referred to
+/// identifiers that could not be found in that scope, the error from
+/// `solve` if the constraints are unsatisfiable, or `TypeError::Holes` if
+/// they're satisfiable but the expression has holes
+pub fn infer_type(
+    environment: &Environment,
+    expr: Ir,
+) -> Result<(Substitutions, TypedIr), TypeError> {
+    let (constraints, mut typed_expr) = generate_constraints(environment, expr)?;
+    let substitutions = solve(constraints)?;
+    typed_expr.apply_subst(&substitutions);
+    let holes = typed_expr.holes(environment);
+    if !holes.is_empty() {
+        return Err(TypeError::Holes(holes));
+    }
+    Ok((substitutions, typed_expr))
+}
+
+/// the constraints `infer_type` solves, along with the typed tree whose type
+/// variables they constrain
+/// # Errors
+/// Returns `TypeError::UnboundIdentifiers` if the expression referred to
+/// identifiers that could not be found in that scope
+pub fn generate_constraints(
+    environment: &Environment,
+    expr: Ir,
+) -> Result<(Constraints, TypedIr), TypeError> {
+    // type variables free in the environment, like those of a `ref` bound
+    // in an earlier session input, are monomorphic throughout
+    let monomorphic_types = (environment.free_vars().into_iter())
+        .map(Type::Unknown)
+        .collect::<TypeSet>();
     let InferStep {
         assumptions,
         constraints,
-        mut typed_expr,
-    } = infer_type_(&TypeSet::default(), expr);
+        typed_expr,
+    } = infer_type_(&monomorphic_types, expr);
 
     // `ids` -- identifiers that couldn't be found in expr need to come from environment
     // `constraints` -- if they are in the environment, make sure that expr's usage of them
@@ -23,20 +51,23 @@ pub fn infer_type(environment: &Environment, expr: Ir) -> (Substitutions, TypedI
     let mut constraints = constraints;
     for (name, t) in &assumptions {
         if let Some(s) = environment.get(name) {
-            constraints.insert_explicit(t.clone(), s.clone());
+            let explicit = ExplicitInstance {
+                instance: t.clone(),
+                scheme: s.clone(),
+            };
+            constraints.insert_for_identifier(t, Constraint::Explicit(explicit));
         } else {
             ids.push(*name);
         }
     }
-    assert!(
-        ids.is_empty(),
-        "unrecognized identifiers: {}",
-        ids.iter().join(", ")
-    );
-
-    let substitutions = solve(constraints);
-    typed_expr.apply_subst(&substitutions);
-    (substitutions, typed_expr)
+    if !ids.is_empty() {
+        // the assumptions are a set, so put them in an order that doesn't
+        // change from run to run
+        ids.sort_unstable();
+        ids.dedup();
+        return Err(TypeError::UnboundIdentifiers(ids));
+    }
+    Ok((constraints, typed_expr))
 }
 
 struct InferStep {
@@ -75,6 +106,14 @@ impl InferStep {
             typed_expr: TypedIr::Id(s, fresh),
         }
     }
+    /// a hole can have any type, which the rest of the program decides
+    pub fn hole(name: Option<&'static str>) -> Self {
+        InferStep {
+            assumptions: Assumptions::default(),
+            constraints: Constraints::default(),
+            typed_expr: TypedIr::Hole(name, fresh_type_var()),
+        }
+    }
     pub fn app(infer1: Self, infer2: Self) -> Self {
         let fresh = fresh_type_var();
         let mut assumptions = infer1.assumptions;
@@ -95,8 +134,8 @@ impl InferStep {
             },
         }
     }
-    pub fn abs(binding: &'static str, body: Self) -> Self {
-        let fresh = fresh_type_var();
+    /// `fresh` - type variable for `binding`, which `body` treated as monomorphic
+    pub fn abs(binding: &'static str, fresh: Type, body: Self) -> Self {
         let Self {
             mut assumptions,
             mut constraints,
@@ -104,7 +143,8 @@ impl InferStep {
         } = body;
         for (name, ty) in &assumptions {
             if *name == binding {
-                constraints.insert_eq(ty.clone(), fresh.clone());
+                let equality = Constraint::Equality(ty.clone(), fresh.clone());
+                constraints.insert_for_identifier(ty, equality);
             }
         }
         assumptions.retain(|(name, _)| *name != binding);
@@ -132,13 +172,16 @@ impl InferStep {
             .cloned()
             .collect_vec()
             .into_boxed_slice();
+        let generalizable = infer1.typed_expr.is_value();
         for (name, ty) in &infer2.assumptions {
             if *name == binding {
-                constraints.insert_implicit(
-                    ty.clone(),
-                    monomorphic_types.clone(),
-                    infer1.typed_expr.ty().clone(),
-                );
+                let implicit = ImplicitInstance {
+                    instance: ty.clone(),
+                    monomorphics: monomorphic_types.clone(),
+                    to_generalize: infer1.typed_expr.ty().clone(),
+                    generalizable,
+                };
+                constraints.insert_for_identifier(ty, Constraint::Implicit(implicit));
             }
         }
 
@@ -181,6 +224,176 @@ impl InferStep {
             typed_expr: TypedIr::Seq(lhs.typed_expr.into(), rhs.typed_expr.into()),
         }
     }
+    pub fn annot(expr: Self, ty: Type) -> Self {
+        let Self {
+            assumptions,
+            mut constraints,
+            typed_expr,
+        } = expr;
+        constraints.insert_eq(typed_expr.ty().clone(), ty.clone());
+        InferStep {
+            assumptions,
+            constraints,
+            typed_expr: TypedIr::Annot {
+                expr: typed_expr.into(),
+                ty,
+            },
+        }
+    }
+    /// constructors have known schemes, so their uses in `e2` become
+    /// explicit instance constraints, just like uses of the environment
+    pub fn data(constructors: Vec<(&'static str, Scheme)>, e2: Self) -> Self {
+        let Self {
+            mut assumptions,
+            mut constraints,
+            typed_expr,
+        } = e2;
+        for (name, ty) in &assumptions {
+            if let Some((_, scheme)) = constructors.iter().find(|(c, _)| c == name) {
+                let explicit = ExplicitInstance {
+                    instance: ty.clone(),
+                    scheme: scheme.clone(),
+                };
+                constraints.insert_for_identifier(ty, Constraint::Explicit(explicit));
+            }
+        }
+        assumptions.retain(|(name, _)| !constructors.iter().any(|(c, _)| c == name));
+        InferStep {
+            assumptions,
+            constraints,
+            typed_expr: TypedIr::Data {
+                constructors,
+                e2: typed_expr.into(),
+            },
+        }
+    }
+    /// the constraints generated since the last location, and the
+    /// identifier if that's what `inner` is, are blamed on `span`
+    pub fn located(span: Span, inner: Self) -> Self {
+        let mut constraints = inner.constraints;
+        constraints.locate(span);
+        if let TypedIr::Id(_, ty) = &inner.typed_expr {
+            constraints.locate_identifier(ty, span);
+        }
+        InferStep {
+            assumptions: inner.assumptions,
+            constraints,
+            typed_expr: TypedIr::Located(span, inner.typed_expr.into()),
+        }
+    }
+    /// every element must have the same type
+    pub fn list(elements: Vec<Self>) -> Self {
+        let fresh = fresh_type_var();
+        let mut assumptions = Assumptions::default();
+        let mut constraints = Constraints::default();
+        let mut typed_elements = Vec::new();
+        for element in elements {
+            assumptions.extend(element.assumptions);
+            constraints.merge(element.constraints);
+            constraints.insert_eq(element.typed_expr.ty().clone(), fresh.clone());
+            typed_elements.push(element.typed_expr);
+        }
+        InferStep {
+            assumptions,
+            constraints,
+            typed_expr: TypedIr::List(typed_elements, Type::list(fresh)),
+        }
+    }
+    /// every pattern must match the scrutinee's type, and every arm must
+    /// have the same type
+    pub fn match_(scrutinee: Self, arms: Vec<ArmStep>) -> Self {
+        let fresh = fresh_type_var();
+        let Self {
+            mut assumptions,
+            mut constraints,
+            typed_expr: scrutinee,
+        } = scrutinee;
+        let mut typed_arms = Vec::new();
+        for arm in arms {
+            constraints.merge(arm.pattern_constraints);
+            constraints.insert_eq(scrutinee.ty().clone(), arm.pattern_ty);
+            let Self {
+                assumptions: mut body_assumptions,
+                constraints: body_constraints,
+                typed_expr: body,
+            } = arm.body;
+            constraints.merge(body_constraints);
+            constraints.insert_eq(body.ty().clone(), fresh.clone());
+            for (name, ty) in &body_assumptions {
+                if let Some((_, bound)) = arm.bindings.iter().find(|(b, _)| b == name) {
+                    let equality = Constraint::Equality(ty.clone(), bound.clone());
+                    constraints.insert_for_identifier(ty, equality);
+                }
+            }
+            body_assumptions.retain(|(name, _)| !arm.bindings.iter().any(|(b, _)| b == name));
+            assumptions.extend(body_assumptions);
+            typed_arms.push((arm.pattern, body));
+        }
+        InferStep {
+            assumptions,
+            constraints,
+            typed_expr: TypedIr::Match {
+                scrutinee: scrutinee.into(),
+                arms: typed_arms,
+                ty: fresh,
+            },
+        }
+    }
+}
+
+/// one arm of a `match`, for `InferStep::match_`
+struct ArmStep {
+    pattern: Pattern,
+    /// type of values the pattern matches
+    pattern_ty: Type,
+    /// constraints imposed by the pattern's structure
+    pattern_constraints: Constraints,
+    /// names bound by the pattern, which are monomorphic in the body
+    bindings: Vec<(&'static str, Type)>,
+    body: InferStep,
+}
+
+impl ArmStep {
+    fn new(monomorphic_types: &TypeSet, pattern: Pattern, body: Ir) -> Self {
+        let mut pattern_constraints = Constraints::new();
+        let mut bindings = Vec::new();
+        let pattern_ty = infer_pattern(&pattern, &mut pattern_constraints, &mut bindings);
+        let monomorphic_types = bindings
+            .iter()
+            .fold(monomorphic_types.clone(), |types, (_, ty)| {
+                with_monomorphic(&types, ty)
+            });
+        ArmStep {
+            pattern,
+            pattern_ty,
+            pattern_constraints,
+            bindings,
+            body: infer_type_(&monomorphic_types, body),
+        }
+    }
+}
+
+/// type of values matched by `pattern`, adding the names it binds to `bindings`
+fn infer_pattern(
+    pattern: &Pattern,
+    constraints: &mut Constraints,
+    bindings: &mut Vec<(&'static str, Type)>,
+) -> Type {
+    match pattern {
+        Pattern::Wildcard => fresh_type_var(),
+        Pattern::Var(name) => {
+            let fresh = fresh_type_var();
+            bindings.push((name, fresh.clone()));
+            fresh
+        }
+        Pattern::Nil => Type::list(fresh_type_var()),
+        Pattern::Cons(head, tail) => {
+            let head = infer_pattern(head, constraints, bindings);
+            let tail = infer_pattern(tail, constraints, bindings);
+            constraints.insert_eq(tail.clone(), Type::list(head));
+            tail
+        }
+    }
 }
 
 fn infer_type_(monomorphic_types: &TypeSet, expr: Ir) -> InferStep {
@@ -190,18 +403,27 @@ fn infer_type_(monomorphic_types: &TypeSet, expr: Ir) -> InferStep {
         LiteralInt(i) => InferStep::literal_int(i),
         LiteralStr(s) => InferStep::literal_str(s),
         Id(s) => InferStep::var(s),
+        Hole(name) => InferStep::hole(name),
         App { e1, e2 } => {
             let infer1 = infer_type_(monomorphic_types, *e1);
             let infer2 = infer_type_(monomorphic_types, *e2);
             InferStep::app(infer1, infer2)
         }
         Lam { binding, body } => {
-            let body = infer_type_(monomorphic_types, *body);
-            InferStep::abs(binding, body)
+            let fresh = fresh_type_var();
+            let body = infer_type_(&with_monomorphic(monomorphic_types, &fresh), *body);
+            InferStep::abs(binding, fresh, body)
         }
         Let { e1, binding, e2 } => {
             let infer1 = infer_type_(monomorphic_types, *e1);
-            let infer2 = infer_type_(monomorphic_types, *e2);
+            // a binding the value restriction keeps from being generalized is
+            // monomorphic in its body, just like a lambda-bound one
+            let infer2 = if infer1.typed_expr.is_value() {
+                infer_type_(monomorphic_types, *e2)
+            } else {
+                let ty = infer1.typed_expr.ty();
+                infer_type_(&with_monomorphic(monomorphic_types, ty), *e2)
+            };
             InferStep::let_(monomorphic_types, infer1, binding, infer2)
         }
         Add(lhs, rhs) => {
@@ -214,5 +436,41 @@ fn infer_type_(monomorphic_types: &TypeSet, expr: Ir) -> InferStep {
             let rhs = infer_type_(monomorphic_types, *rhs);
             InferStep::seq(lhs, rhs)
         }
+        Annot { expr, ty } => {
+            let expr = infer_type_(monomorphic_types, *expr);
+            InferStep::annot(expr, ty)
+        }
+        Data { constructors, e2 } => {
+            let e2 = infer_type_(monomorphic_types, *e2);
+            InferStep::data(constructors, e2)
+        }
+        List(elements) => {
+            let elements = elements
+                .into_iter()
+                .map(|element| infer_type_(monomorphic_types, element))
+                .collect();
+            InferStep::list(elements)
+        }
+        Match { scrutinee, arms } => {
+            let scrutinee = infer_type_(monomorphic_types, *scrutinee);
+            let arms = arms
+                .into_iter()
+                .map(|(pattern, body)| ArmStep::new(monomorphic_types, pattern, body))
+                .collect();
+            InferStep::match_(scrutinee, arms)
+        }
+        Located(span, inner) => {
+            let inner = infer_type_(monomorphic_types, *inner);
+            InferStep::located(span, inner)
+        }
     }
 }
+
+/// helper to add a type to the monomorphic set for an inner scope
+fn with_monomorphic(monomorphic_types: &TypeSet, ty: &Type) -> TypeSet {
+    monomorphic_types
+        .iter()
+        .chain(std::iter::once(ty))
+        .cloned()
+        .collect()
+}
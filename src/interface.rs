@@ -0,0 +1,70 @@
+//! Interface files, which record the schemes a module exports so importers
+//! don't have to infer it again.
+//!
+//! ```text
+//! -- hash 5c1b3a0e27f1d9e4
+//! area : Shape -> Int
+//! map : forall a b. (a -> b) -> List a -> List b
+//! ```
+//!
+//! They're declarations, as read by `parse_environment`, after a hash that
+//! identifies the source they were inferred from. Every type variable in
+//! them is quantified, since a module can't export a binding the value
+//! restriction kept from being generalized.
+
+use std::fmt;
+
+use crate::{environment_to_string, parse_environment, Environment, ParseError, Span};
+
+/// Exported schemes of a module, and a hash of what they were inferred from
+#[derive(Debug, Clone)]
+pub struct Interface {
+    pub hash: u64,
+    pub exports: Environment,
+}
+
+impl Interface {
+    #[must_use]
+    pub fn new(hash: u64, exports: Environment) -> Self {
+        Self { hash, exports }
+    }
+
+    /// read an interface written by `to_string`
+    /// # Errors
+    /// Returns a `ParseError` if it isn't a list of declarations following a
+    /// hash
+    pub fn parse(text: &str) -> Result<Self, ParseError> {
+        let hash = (text.lines().next())
+            .and_then(|line| line.strip_prefix("-- hash "))
+            .and_then(|hash| u64::from_str_radix(hash.trim(), 16).ok())
+            .ok_or_else(|| ParseError {
+                span: Span::new(0, 0),
+                message: "expected `-- hash` line".to_owned(),
+            })?;
+        Ok(Self {
+            hash,
+            exports: parse_environment(text)?,
+        })
+    }
+}
+
+impl fmt::Display for Interface {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "-- hash {:016x}", self.hash)?;
+        write!(f, "{}", environment_to_string(&self.exports))
+    }
+}
+
+/// FNV-1a hash of `source` and the hashes of what it depends on. Unlike
+/// `std`'s hasher, it's the same on every platform and release
+#[must_use]
+pub(crate) fn content_hash(source: &str, dependencies: impl IntoIterator<Item = u64>) -> u64 {
+    const PRIME: u64 = 0x0100_0000_01b3;
+    let mut hash = 0xcbf2_9ce4_8422_2325_u64;
+    let dependencies = dependencies.into_iter().flat_map(u64::to_le_bytes);
+    for byte in source.bytes().chain(dependencies) {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(PRIME);
+    }
+    hash
+}
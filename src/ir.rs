@@ -1,24 +1,73 @@
-#[derive(Debug)]
+use crate::{hole::hole_text, Name, Pattern, Scheme, Span, Type};
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Ir {
     Nop,
-    LiteralStr(&'static str),
+    LiteralStr(
+        #[cfg_attr(
+            feature = "serde",
+            serde(deserialize_with = "crate::serde_support::name")
+        )]
+        Name,
+    ),
     LiteralInt(i64),
-    Id(&'static str),
+    Id(
+        #[cfg_attr(
+            feature = "serde",
+            serde(deserialize_with = "crate::serde_support::name")
+        )]
+        Name,
+    ),
     App {
         e1: Box<Ir>,
         e2: Box<Ir>,
     },
     Lam {
-        binding: &'static str,
+        #[cfg_attr(
+            feature = "serde",
+            serde(deserialize_with = "crate::serde_support::name")
+        )]
+        binding: Name,
         body: Box<Ir>,
     },
     Let {
         e1: Box<Ir>,
-        binding: &'static str,
+        #[cfg_attr(
+            feature = "serde",
+            serde(deserialize_with = "crate::serde_support::name")
+        )]
+        binding: Name,
         e2: Box<Ir>,
     },
     Add(Box<Ir>, Box<Ir>),
     Seq(Box<Ir>, Box<Ir>),
+    /// expression that must have the given type
+    Annot {
+        expr: Box<Ir>,
+        ty: Type,
+    },
+    /// constructor functions of a data declaration, scoped over `e2`
+    Data {
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_support::constructors"))]
+        constructors: Vec<(Name, Scheme)>,
+        e2: Box<Ir>,
+    },
+    List(Vec<Ir>),
+    /// `_` or `?name`, an expression yet to be written
+    Hole(
+        #[cfg_attr(
+            feature = "serde",
+            serde(deserialize_with = "crate::serde_support::optional_name")
+        )]
+        Option<Name>,
+    ),
+    Match {
+        scrutinee: Box<Ir>,
+        arms: Vec<(Pattern, Ir)>,
+    },
+    /// where the wrapped node came from in the source text
+    Located(Span, Box<Ir>),
 }
 
 impl Ir {
@@ -28,10 +77,12 @@ impl Ir {
     fn display_tree_(&self, prefix: &str) {
         use Ir::*;
         match self {
+            Located(_, inner) => inner.display_tree_(prefix),
             Nop => println!("{prefix}+-NOP"),
             LiteralStr(s) => println!("{prefix}+-\"{s}\""),
             LiteralInt(i) => println!("{prefix}+-{i}"),
             Id(s) => println!("{prefix}+-ID `{s}`"),
+            Hole(name) => println!("{prefix}+-HOLE {}", hole_text(*name)),
             App { e1, e2 } => {
                 println!("{prefix}+-App");
                 println!("{prefix}  |  |");
@@ -65,6 +116,38 @@ impl Ir {
                 println!("{prefix}  |");
                 e2.display_tree_(&format!("{prefix}  "));
             }
+            Annot { expr, ty } => {
+                println!("{prefix}+-Annot ... : {ty}");
+                println!("{prefix}  |");
+                expr.display_tree_(&format!("{prefix}  "));
+            }
+            Data { constructors, e2 } => {
+                let names = constructors
+                    .iter()
+                    .map(|(name, _)| *name)
+                    .collect::<Vec<_>>();
+                println!("{prefix}+-Data {} in ...", names.join(" | "));
+                println!("{prefix}  |");
+                e2.display_tree_(&format!("{prefix}  "));
+            }
+            List(elements) => {
+                println!("{prefix}+-List");
+                for element in elements {
+                    println!("{prefix}  |");
+                    element.display_tree_(&format!("{prefix}  |"));
+                }
+            }
+            Match { scrutinee, arms } => {
+                println!("{prefix}+-Match");
+                println!("{prefix}  |  |");
+                scrutinee.display_tree_(&format!("{prefix}  |  "));
+                for (pattern, arm) in arms {
+                    println!("{prefix}  |");
+                    println!("{prefix}  +-{pattern} -> ...");
+                    println!("{prefix}  |  |");
+                    arm.display_tree_(&format!("{prefix}  |  "));
+                }
+            }
         }
     }
 }
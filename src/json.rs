@@ -0,0 +1,258 @@
+//! Just enough JSON for the language server's messages
+
+use std::fmt::{self, Write};
+
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    /// keys in the order they were written
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// build an object from key-value pairs
+    pub fn object<const N: usize>(fields: [(&str, Json); N]) -> Json {
+        Json::Object(
+            fields
+                .into_iter()
+                .map(|(key, value)| (key.to_owned(), value))
+                .collect(),
+        )
+    }
+    /// member of an object, or `Null` if it's absent or this isn't an object
+    pub fn get(&self, key: &str) -> &Json {
+        match self {
+            Json::Object(fields) => fields
+                .iter()
+                .find(|(k, _)| k == key)
+                .map_or(&Json::Null, |(_, value)| value),
+            _ => &Json::Null,
+        }
+    }
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+    pub fn as_usize(&self) -> Option<usize> {
+        match self {
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            Json::Number(n) if *n >= 0.0 && n.fract() == 0.0 => Some(*n as usize),
+            _ => None,
+        }
+    }
+    /// parse a complete JSON document
+    pub fn parse(text: &str) -> Result<Json, String> {
+        let mut parser = JsonParser { text, pos: 0 };
+        let value = parser.value()?;
+        parser.whitespace();
+        if parser.pos == text.len() {
+            Ok(value)
+        } else {
+            Err(format!("trailing characters at {}", parser.pos))
+        }
+    }
+}
+
+impl From<&str> for Json {
+    fn from(s: &str) -> Self {
+        Json::String(s.to_owned())
+    }
+}
+
+impl From<String> for Json {
+    fn from(s: String) -> Self {
+        Json::String(s)
+    }
+}
+
+impl From<usize> for Json {
+    #[allow(clippy::cast_precision_loss)] // positions are nowhere near 2^52
+    fn from(n: usize) -> Self {
+        Json::Number(n as f64)
+    }
+}
+
+impl From<bool> for Json {
+    fn from(b: bool) -> Self {
+        Json::Bool(b)
+    }
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(b) => write!(f, "{b}"),
+            Json::Number(n) => write!(f, "{n}"),
+            Json::String(s) => write_string(f, s),
+            Json::Array(elements) => {
+                f.write_char('[')?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "{element}")?;
+                }
+                f.write_char(']')
+            }
+            Json::Object(fields) => {
+                f.write_char('{')?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{value}")?;
+                }
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+/// `s` as a quoted JSON string
+fn write_string(f: &mut impl Write, s: &str) -> fmt::Result {
+    f.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            c if c.is_control() => write!(f, "\\u{:04x}", u32::from(c))?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+struct JsonParser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl JsonParser<'_> {
+    fn rest(&self) -> &str {
+        &self.text[self.pos..]
+    }
+    fn whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+    fn eat(&mut self, token: &str) -> bool {
+        self.whitespace();
+        if self.rest().starts_with(token) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+    fn expect(&mut self, token: &str) -> Result<(), String> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(format!("expected `{token}` at {}", self.pos))
+        }
+    }
+    fn value(&mut self) -> Result<Json, String> {
+        self.whitespace();
+        if self.eat("null") {
+            Ok(Json::Null)
+        } else if self.eat("true") {
+            Ok(Json::Bool(true))
+        } else if self.eat("false") {
+            Ok(Json::Bool(false))
+        } else if self.rest().starts_with('"') {
+            self.string().map(Json::String)
+        } else if self.eat("[") {
+            let mut elements = Vec::new();
+            if !self.eat("]") {
+                loop {
+                    elements.push(self.value()?);
+                    if !self.eat(",") {
+                        break;
+                    }
+                }
+                self.expect("]")?;
+            }
+            Ok(Json::Array(elements))
+        } else if self.eat("{") {
+            let mut fields = Vec::new();
+            if !self.eat("}") {
+                loop {
+                    self.whitespace();
+                    let key = self.string()?;
+                    self.expect(":")?;
+                    fields.push((key, self.value()?));
+                    if !self.eat(",") {
+                        break;
+                    }
+                }
+                self.expect("}")?;
+            }
+            Ok(Json::Object(fields))
+        } else {
+            let len = self
+                .rest()
+                .find(|c: char| !(c.is_ascii_digit() || "+-.eE".contains(c)))
+                .unwrap_or(self.rest().len());
+            let number = self.rest()[..len]
+                .parse()
+                .map_err(|_| format!("unexpected character at {}", self.pos))?;
+            self.pos += len;
+            Ok(Json::Number(number))
+        }
+    }
+    fn string(&mut self) -> Result<String, String> {
+        self.expect("\"")?;
+        let mut s = String::new();
+        let mut chars = self.rest().char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.pos += i + 1;
+                    return Ok(s);
+                }
+                '\\' => match chars.next().map(|(_, c)| c) {
+                    Some('n') => s.push('\n'),
+                    Some('t') => s.push('\t'),
+                    Some('r') => s.push('\r'),
+                    Some('b') => s.push('\u{8}'),
+                    Some('f') => s.push('\u{c}'),
+                    Some('u') => {
+                        let bad = || format!("bad escape at {}", self.pos + i);
+                        let first = hex_unit(&mut chars).ok_or_else(bad)?;
+                        // characters outside the Basic Multilingual Plane, like
+                        // emoji, are escaped as a pair of UTF-16 surrogates
+                        let mut units = vec![first];
+                        if (0xd800..0xdc00).contains(&first) && chars.as_str().starts_with("\\u") {
+                            chars.nth(1);
+                            units.push(hex_unit(&mut chars).ok_or_else(bad)?);
+                        }
+                        // a surrogate without its other half isn't a character
+                        s.extend(
+                            char::decode_utf16(units)
+                                .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER)),
+                        );
+                    }
+                    Some(c) => s.push(c),
+                    None => break,
+                },
+                c => s.push(c),
+            }
+        }
+        Err("unterminated string".to_owned())
+    }
+}
+
+/// the UTF-16 code unit written as four hex digits after `\u`
+fn hex_unit(chars: &mut std::str::CharIndices) -> Option<u16> {
+    let hex: String = chars.take(4).map(|(_, c)| c).collect();
+    u16::from_str_radix(&hex, 16).ok()
+}
@@ -0,0 +1,174 @@
+use std::{collections::HashMap, fmt};
+
+use crate::{TypeError, TypeExpr, TypeScope};
+
+/// The "type of a type". `Int` has kind `*`, `List` has kind `* -> *`
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub enum Kind {
+    Star,
+    Arrow(Box<Kind>, Box<Kind>),
+    Unknown(u32),
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Kind::*;
+        match self {
+            Star => write!(f, "*"),
+            Arrow(param, result) if matches!(**param, Arrow(..)) => {
+                write!(f, "({param}) -> {result}")
+            }
+            Arrow(param, result) => write!(f, "{param} -> {result}"),
+            Unknown(id) => write!(f, "k{id}"),
+        }
+    }
+}
+
+/// Infer the kinds of a data declaration's type constructor and its
+/// parameters. Parameters whose kind is unconstrained default to `*`.
+/// # Errors
+/// Returns `TypeError::KindMismatch` if the constructor fields are not
+/// well-kinded, or `TypeError::UnknownType` if they mention an undeclared name
+pub fn infer_data_kind(
+    scope: &TypeScope,
+    name: &'static str,
+    params: &[&'static str],
+    constructors: &[(&'static str, Vec<TypeExpr>)],
+) -> Result<(Kind, Vec<Kind>), TypeError> {
+    let mut inference = KindInference::new(scope);
+    let data_kind = inference.fresh();
+    let param_kinds = params.iter().map(|_| inference.fresh()).collect::<Vec<_>>();
+    inference.locals.insert(name, data_kind.clone());
+    inference
+        .locals
+        .extend(params.iter().copied().zip(param_kinds.iter().cloned()));
+
+    let expected = param_kinds.iter().rev().fold(Kind::Star, |result, param| {
+        Kind::Arrow(param.clone().into(), result.into())
+    });
+    inference.unify(&data_kind, &expected)?;
+    for field in constructors.iter().flat_map(|(_, fields)| fields) {
+        let field_kind = inference.infer(field)?;
+        inference.unify(&field_kind, &Kind::Star)?;
+    }
+    Ok((
+        inference.finish(&data_kind),
+        param_kinds.iter().map(|k| inference.finish(k)).collect(),
+    ))
+}
+
+/// Infer the kind of a type expression, defaulting unconstrained parts to `*`
+/// # Errors
+/// Returns `TypeError::KindMismatch` if the expression is not well-kinded
+pub fn infer_kind(scope: &TypeScope, ty: &TypeExpr) -> Result<Kind, TypeError> {
+    let mut inference = KindInference::new(scope);
+    let kind = inference.infer(ty)?;
+    Ok(inference.finish(&kind))
+}
+
+/// Check that a type expression describes values, i.e. has kind `*`
+/// # Errors
+/// Returns `TypeError::KindMismatch` if it has any other kind
+pub fn check_kind_star(scope: &TypeScope, ty: &TypeExpr) -> Result<(), TypeError> {
+    let mut inference = KindInference::new(scope);
+    let kind = inference.infer(ty)?;
+    inference.unify(&kind, &Kind::Star)
+}
+
+/// unification-based kind inference state
+struct KindInference<'a> {
+    scope: &'a TypeScope,
+    /// names being declared, which aren't in `scope` yet
+    locals: HashMap<&'static str, Kind>,
+    subs: HashMap<u32, Kind>,
+    next_id: u32,
+}
+
+impl<'a> KindInference<'a> {
+    fn new(scope: &'a TypeScope) -> Self {
+        Self {
+            scope,
+            locals: HashMap::new(),
+            subs: HashMap::new(),
+            next_id: 0,
+        }
+    }
+    fn fresh(&mut self) -> Kind {
+        self.next_id += 1;
+        Kind::Unknown(self.next_id)
+    }
+    fn infer(&mut self, ty: &TypeExpr) -> Result<Kind, TypeError> {
+        match ty {
+            TypeExpr::Named(name) => self
+                .locals
+                .get(name)
+                .or_else(|| self.scope.kind(name))
+                .cloned()
+                .ok_or(TypeError::UnknownType(name)),
+            TypeExpr::F(param, result) => {
+                let param = self.infer(param)?;
+                self.unify(&param, &Kind::Star)?;
+                let result = self.infer(result)?;
+                self.unify(&result, &Kind::Star)?;
+                Ok(Kind::Star)
+            }
+            TypeExpr::App(constructor, arg) => {
+                let constructor = self.infer(constructor)?;
+                let arg = self.infer(arg)?;
+                let result = self.fresh();
+                self.unify(
+                    &constructor,
+                    &Kind::Arrow(arg.into(), result.clone().into()),
+                )?;
+                Ok(result)
+            }
+        }
+    }
+    /// follow substitutions until reaching a kind that isn't a bound variable
+    fn resolve(&self, kind: &Kind) -> Kind {
+        match kind {
+            Kind::Unknown(id) => match self.subs.get(id) {
+                Some(bound) => self.resolve(bound),
+                None => kind.clone(),
+            },
+            Kind::Arrow(param, result) => {
+                Kind::Arrow(self.resolve(param).into(), self.resolve(result).into())
+            }
+            Kind::Star => Kind::Star,
+        }
+    }
+    fn unify(&mut self, k1: &Kind, k2: &Kind) -> Result<(), TypeError> {
+        use Kind::*;
+        let (k1, k2) = (self.resolve(k1), self.resolve(k2));
+        match (&k1, &k2) {
+            (Star, Star) => Ok(()),
+            (Unknown(id1), Unknown(id2)) if id1 == id2 => Ok(()),
+            (Unknown(id), other) | (other, Unknown(id)) if !occurs(*id, other) => {
+                self.subs.insert(*id, other.clone());
+                Ok(())
+            }
+            (Arrow(param1, result1), Arrow(param2, result2)) => {
+                self.unify(param1, param2)?;
+                self.unify(result1, result2)
+            }
+            _ => Err(TypeError::KindMismatch(k1, k2)),
+        }
+    }
+    /// resolve a kind, defaulting any remaining unknowns to `*`
+    fn finish(&self, kind: &Kind) -> Kind {
+        match self.resolve(kind) {
+            Kind::Unknown(_) | Kind::Star => Kind::Star,
+            Kind::Arrow(param, result) => {
+                Kind::Arrow(self.finish(&param).into(), self.finish(&result).into())
+            }
+        }
+    }
+}
+
+fn occurs(id: u32, kind: &Kind) -> bool {
+    match kind {
+        Kind::Star => false,
+        Kind::Unknown(other) => id == *other,
+        Kind::Arrow(param, result) => occurs(id, param) || occurs(id, result),
+    }
+}
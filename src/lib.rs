@@ -1,34 +1,82 @@
 #![warn(clippy::pedantic)]
 #![allow(clippy::enum_glob_use)]
+mod algorithm_w;
 mod apply_subst;
 mod ast;
 mod constraints;
+mod discarded;
+mod dot;
+mod eval;
 mod free_vars;
 mod generalize;
+mod hole;
+mod incremental;
 mod infer_type;
 mod instantiate;
+mod interface;
 mod ir;
+mod json;
+mod kind;
+mod lsp;
 mod mgu;
+mod module;
+mod parser;
+mod pattern;
+mod prelude;
+#[cfg(feature = "serde")]
+mod serde_support;
+mod session;
+mod slice;
 mod solve;
+mod span;
+mod trace;
 mod r#type;
+mod type_error;
+mod type_expr;
 mod typedir;
+mod unsatisfiable;
 
 use std::collections::{HashMap, HashSet};
 
+pub use algorithm_w::algorithm_w;
 pub use apply_subst::ApplySubst;
 pub use ast::Ast;
 pub use constraints::*;
+pub use discarded::*;
+pub use eval::*;
 pub use free_vars::*;
 pub use generalize::*;
+pub use hole::Hole;
+pub use incremental::{Checked, IncrementalInference};
 pub use infer_type::*;
 pub use instantiate::*;
+pub use interface::Interface;
 pub use ir::Ir;
+pub use kind::*;
+pub use lsp::serve_language_server;
 pub use mgu::mgu;
-use r#type::Scheme;
-pub use r#type::Type;
-pub use solve::solve;
+pub use module::{Module, ModuleError, ModuleLoader};
+pub use parser::{parse, parse_expr, ParseError};
+pub use pattern::Pattern;
+pub use prelude::{default_environment, environment_to_string, parse_environment};
+pub use r#type::{scheme_to_string, Scheme, Type};
+#[cfg(feature = "serde")]
+pub use serde_support::{
+    deserialize_environment, deserialize_scheme, serialize_environment, serialize_scheme,
+};
+pub use session::{Session, SessionError};
+pub use slice::type_error_slice;
+pub use solve::{solve, solve_traced};
+pub use span::Span;
+pub use trace::SolveEvent;
+pub use type_error::TypeError;
+pub use type_expr::{TypeExpr, TypeScope};
 pub use typedir::TypedIr;
+pub use unsatisfiable::minimal_unsatisfiable_subset;
 
+/// identifier or type name. Names read from source text are interned (see
+/// `intern`), so they can be copied and compared cheaply
+pub type Name = &'static str;
 type Assumptions = HashSet<(&'static str, Type)>;
 pub type Environment = HashMap<&'static str, Scheme>;
 /// not using an actual Set type because it needs to be hashable
@@ -57,3 +105,20 @@ pub fn fresh_type_id() -> u32 {
 pub fn fresh_type_var() -> Type {
     Type::Unknown(fresh_type_id())
 }
+
+/// get a `'static` copy of a name, such as an identifier read from source
+/// text. Each distinct name is only leaked once
+pub fn intern(name: &str) -> &'static str {
+    use std::sync::{Mutex, OnceLock, PoisonError};
+    static NAMES: OnceLock<Mutex<HashSet<&'static str>>> = OnceLock::new();
+    let mut names = NAMES
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    if let Some(interned) = names.get(name) {
+        return interned;
+    }
+    let interned: &'static str = Box::leak(name.into());
+    names.insert(interned);
+    interned
+}
@@ -0,0 +1,577 @@
+//! A language server speaking the Language Server Protocol over JSON-RPC.
+//! It keeps each open document typechecked, re-inferring only the statements
+//! an edit affects, and publishes errors as diagnostics (a type error on
+//! every part of its slice). It answers hover requests with the type of the
+//! expression under the cursor and go-to-definition requests for `let`-bound
+//! names.
+//!
+//! A document's imports are read from the `.hhs` files next to it. Each is
+//! only read once, so edits to an imported module take effect when the
+//! server restarts.
+
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Write},
+    path::PathBuf,
+};
+
+use crate::{
+    fresh_type_id, infer_type, json::Json, parse, type_error_slice, Ast, Environment,
+    IncrementalInference, ModuleError, ModuleLoader, Name, Span, Type, TypeError, TypedIr,
+};
+
+/// answer requests read from `input` until the client sends `exit` or closes
+/// the stream. Every document starts with `environment` in scope, along
+/// with what it imports
+/// # Errors
+/// Returns any error reading or writing the streams, or one for a message
+/// that isn't framed as the protocol requires. A message whose body isn't
+/// JSON gets a parse error in reply, and the server carries on
+pub fn serve_language_server(
+    mut input: impl BufRead,
+    output: impl Write,
+    environment: Environment,
+) -> io::Result<()> {
+    let mut server = Server {
+        out: output,
+        environment,
+        loaders: HashMap::new(),
+        documents: HashMap::new(),
+    };
+    while let Some(message) = read_message(&mut input)? {
+        let message = match message {
+            Ok(message) => message,
+            Err(e) => {
+                server.send(&error_response(Json::Null, -32700, &e))?;
+                continue;
+            }
+        };
+        if message.get("method").as_str() == Some("exit") {
+            break;
+        }
+        server.handle(&message)?;
+    }
+    Ok(())
+}
+
+/// the next message, or why its body can't be read as JSON
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<Result<Json, String>>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = value.trim().parse().ok();
+        }
+    }
+    let length: usize = length.ok_or_else(|| invalid("missing Content-Length"))?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    let body = String::from_utf8(body).map_err(|e| e.to_string());
+    Ok(Some(body.and_then(|body| Json::parse(&body))))
+}
+
+/// a JSON-RPC error answering the request `id`
+fn error_response(id: Json, code: i32, message: &str) -> Json {
+    Json::object([
+        ("jsonrpc", "2.0".into()),
+        ("id", id),
+        (
+            "error",
+            Json::object([
+                ("code", Json::Number(code.into())),
+                ("message", message.into()),
+            ]),
+        ),
+    ])
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+struct Server<W> {
+    out: W,
+    environment: Environment,
+    /// loads the modules documents import, by the directory they're in
+    loaders: HashMap<Option<PathBuf>, ModuleLoader>,
+    documents: HashMap<String, Document>,
+}
+
+struct Document {
+    text: String,
+    /// `None` if the document doesn't typecheck
+    typed: Option<TypedIr>,
+    /// what was inferred for the document's statements, so an edit only
+    /// re-infers the ones it affects
+    inference: IncrementalInference,
+}
+
+impl<W: Write> Server<W> {
+    fn send(&mut self, message: &Json) -> io::Result<()> {
+        let body = message.to_string();
+        write!(self.out, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+        self.out.flush()
+    }
+
+    fn handle(&mut self, message: &Json) -> io::Result<()> {
+        let params = message.get("params");
+        let uri = params.get("textDocument").get("uri").as_str().unwrap_or("");
+        let result = match message.get("method").as_str().unwrap_or("") {
+            "initialize" => Json::object([(
+                "capabilities",
+                Json::object([
+                    // full text on every change
+                    ("textDocumentSync", 1.into()),
+                    ("hoverProvider", true.into()),
+                    ("definitionProvider", true.into()),
+                ]),
+            )]),
+            "textDocument/didOpen" => {
+                let text = params.get("textDocument").get("text").as_str();
+                return self.update(uri, text.unwrap_or(""));
+            }
+            "textDocument/didChange" => {
+                let text = match params.get("contentChanges") {
+                    Json::Array(changes) => changes.last().and_then(|c| c.get("text").as_str()),
+                    _ => None,
+                };
+                return self.update(uri, text.unwrap_or(""));
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                return self.publish_diagnostics(uri, &[]);
+            }
+            "textDocument/hover" => self.hover(uri, params.get("position")),
+            "textDocument/definition" => self.definition(uri, params.get("position")),
+            "shutdown" => Json::Null,
+            method => {
+                if message.get("id") != &Json::Null {
+                    let id = message.get("id").clone();
+                    let unsupported = format!("unsupported method `{method}`");
+                    return self.send(&error_response(id, -32601, &unsupported));
+                }
+                // other notifications need no answer
+                return Ok(());
+            }
+        };
+        self.send(&Json::object([
+            ("jsonrpc", "2.0".into()),
+            ("id", message.get("id").clone()),
+            ("result", result),
+        ]))
+    }
+
+    /// re-check a document whose text changed
+    fn update(&mut self, uri: &str, text: &str) -> io::Result<()> {
+        let mut inference = match self.documents.remove(uri) {
+            Some(document) => document.inference,
+            None => IncrementalInference::new(self.environment.clone()),
+        };
+        let checked = parse(text)
+            .map_err(|e| (e.span, e.to_string()))
+            .and_then(|ast| {
+                let environment = self.environment_for(uri, &ast)?;
+                // what was inferred in a different environment is no use
+                if inference.environment() != &environment {
+                    inference = IncrementalInference::new(environment);
+                }
+                Ok(check(text, ast, &mut inference))
+            });
+        let (typed, diagnostics) = checked.unwrap_or_else(|diagnostic| (None, vec![diagnostic]));
+        self.documents.insert(
+            uri.to_owned(),
+            Document {
+                text: text.to_owned(),
+                typed,
+                inference,
+            },
+        );
+        self.publish_diagnostics(uri, &diagnostics)
+    }
+
+    fn publish_diagnostics(&mut self, uri: &str, diagnostics: &[(Span, String)]) -> io::Result<()> {
+        let text = self.documents.get(uri).map_or("", |d| d.text.as_str());
+        let diagnostics = diagnostics
+            .iter()
+            .map(|(span, message)| {
+                Json::object([
+                    ("range", range(text, *span)),
+                    // error
+                    ("severity", 1.into()),
+                    ("source", "heeren_hage_swierstra".into()),
+                    ("message", message.as_str().into()),
+                ])
+            })
+            .collect();
+        self.send(&Json::object([
+            ("jsonrpc", "2.0".into()),
+            ("method", "textDocument/publishDiagnostics".into()),
+            (
+                "params",
+                Json::object([
+                    ("uri", uri.into()),
+                    ("diagnostics", Json::Array(diagnostics)),
+                ]),
+            ),
+        ]))
+    }
+
+    /// type of the smallest located expression under the cursor
+    fn hover(&self, uri: &str, position: &Json) -> Json {
+        let Some((document, offset)) = self.locate(uri, position) else {
+            return Json::Null;
+        };
+        let Some(typed) = &document.typed else {
+            return Json::Null;
+        };
+        let mut best: Option<(Span, &TypedIr)> = None;
+        visit_located(typed, &mut |span, node| {
+            let contains = span.start <= offset && offset < span.end;
+            if contains && best.is_none_or(|(b, _)| span.end - span.start <= b.end - b.start) {
+                best = Some((span, node));
+            }
+        });
+        let Some((span, node)) = best else {
+            return Json::Null;
+        };
+        let hover = match node {
+            TypedIr::Id(name, ty) => format!("{name} : {ty}"),
+            node => node.ty().to_string(),
+        };
+        Json::object([
+            (
+                "contents",
+                Json::object([
+                    ("kind", "markdown".into()),
+                    ("value", format!("```\n{hover}\n```").into()),
+                ]),
+            ),
+            ("range", range(&document.text, span)),
+        ])
+    }
+
+    /// where the `let`, `fn` or `data` declaring the name under the cursor is
+    fn definition(&self, uri: &str, position: &Json) -> Json {
+        let Some((document, offset)) = self.locate(uri, position) else {
+            return Json::Null;
+        };
+        let Some(typed) = &document.typed else {
+            return Json::Null;
+        };
+        let mut found = None;
+        resolve_names(typed, None, &mut Vec::new(), &mut |span, name, binder| {
+            if span.start <= offset && offset < span.end {
+                if let Binder::Declared(declaration) = binder {
+                    found = Some(name_span(&document.text, declaration, name));
+                }
+            }
+        });
+        found.map_or(Json::Null, |span| {
+            Json::object([("uri", uri.into()), ("range", range(&document.text, span))])
+        })
+    }
+
+    /// what's in scope for the document at `uri`, whose syntax tree is `ast`,
+    /// or a diagnostic on the import that couldn't be loaded
+    fn environment_for(&mut self, uri: &str, ast: &Ast) -> Result<Environment, (Span, String)> {
+        let path = file_path(uri);
+        let name = (path.as_ref())
+            .and_then(|path| path.file_stem()?.to_str())
+            .unwrap_or("Main");
+        let directory = path
+            .as_ref()
+            .and_then(|path| Some(path.parent()?.to_owned()));
+        let loader =
+            (self.loaders.entry(directory)).or_insert_with_key(|directory| match directory {
+                Some(directory) => ModuleLoader::in_directory(directory, self.environment.clone()),
+                None => ModuleLoader::new(self.environment.clone(), |_| {
+                    Err(io::Error::new(
+                        io::ErrorKind::NotFound,
+                        "the document isn't a file",
+                    ))
+                }),
+            });
+        loader.environment_for(name, ast).map_err(|e| {
+            let module = match &e {
+                ModuleError::Cycle(modules) => modules.get(1).copied(),
+                ModuleError::Read { module, .. }
+                | ModuleError::Parse { module, .. }
+                | ModuleError::Type { module, .. }
+                | ModuleError::UnknownExport { module, .. }
+                | ModuleError::NotExported { module, .. }
+                | ModuleError::WeakExport { module, .. } => Some(*module),
+            };
+            let span = module.and_then(|module| import_span(ast, module));
+            (span.unwrap_or_default(), e.to_string())
+        })
+    }
+
+    fn locate(&self, uri: &str, position: &Json) -> Option<(&Document, usize)> {
+        let document = self.documents.get(uri)?;
+        let line = position.get("line").as_usize()?;
+        let character = position.get("character").as_usize()?;
+        Some((document, offset(&document.text, line, character)))
+    }
+}
+
+/// typecheck a document, parsed as `ast`, returning the typed tree if it's
+/// well typed and diagnostics if it isn't
+fn check(
+    text: &str,
+    ast: Ast,
+    inference: &mut IncrementalInference,
+) -> (Option<TypedIr>, Vec<(Span, String)>) {
+    let checked = (ast.clone().desugar_program()).and_then(|ir| inference.check(text, ir));
+    match checked {
+        Ok(checked) => (Some(checked.typed), Vec::new()),
+        // the whole document is inferred again to explain the error
+        Err(_) => (None, diagnose(text, ast, inference.environment())),
+    }
+}
+
+/// diagnostics for a document that doesn't typecheck
+fn diagnose(text: &str, ast: Ast, environment: &Environment) -> Vec<(Span, String)> {
+    let infer = |environment: &Environment, ast: Ast| -> Result<TypedIr, TypeError> {
+        Ok(infer_type(environment, ast.desugar_program()?)?.1)
+    };
+    let whole = Span::new(0, 0);
+    match infer(environment, ast.clone()) {
+        Ok(_) => Vec::new(),
+        Err(TypeError::UnboundIdentifiers(ids)) => {
+            // give the unbound names any type, to find where they're used
+            let mut environment = environment.clone();
+            for id in &ids {
+                let var = fresh_type_id();
+                environment.insert(id, ([var].into(), Type::Unknown(var)));
+            }
+            let Ok(typed) = infer(&environment, ast) else {
+                let error = TypeError::UnboundIdentifiers(ids);
+                return vec![(whole, error.to_string())];
+            };
+            let mut diagnostics = Vec::new();
+            resolve_names(&typed, None, &mut Vec::new(), &mut |span, name, binder| {
+                if binder == Binder::Unbound && ids.contains(&name) {
+                    diagnostics.push((span, format!("unrecognized identifier `{name}`")));
+                }
+            });
+            diagnostics
+        }
+        Err(TypeError::Holes(holes)) => (holes.iter())
+            .map(|hole| (hole.span.unwrap_or(whole), hole.to_string()))
+            .collect(),
+        Err(e) => {
+            // highlight every part of the program the error comes from
+            let slice = (ast.desugar_program().ok())
+                .and_then(|ir| type_error_slice(environment, ir))
+                .unwrap_or_default();
+            let mut diagnostics = (slice.into_iter())
+                .filter_map(|span| trimmed(text, span))
+                .map(|span| (span, e.to_string()))
+                .collect::<Vec<_>>();
+            if diagnostics.is_empty() {
+                diagnostics.push((whole, e.to_string()));
+            }
+            diagnostics
+        }
+    }
+}
+
+/// the file a `file:` URI names
+fn file_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    // characters outside the URI syntax are written `%20` and so on
+    let mut bytes = Vec::new();
+    let mut rest = path.as_bytes();
+    while let Some((&byte, after)) = rest.split_first() {
+        let escaped = (byte == b'%')
+            .then(|| std::str::from_utf8(after.get(..2)?).ok())
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        if let Some(escaped) = escaped {
+            bytes.push(escaped);
+            rest = &after[2..];
+        } else {
+            bytes.push(byte);
+            rest = after;
+        }
+    }
+    String::from_utf8(bytes).ok().map(PathBuf::from)
+}
+
+/// location of the statement in `ast` importing `module`
+fn import_span(ast: &Ast, module: Name) -> Option<Span> {
+    let Ast::Located(_, program) = ast else {
+        return None;
+    };
+    let Ast::Do(statements) = &**program else {
+        return None;
+    };
+    statements.iter().find_map(|statement| match statement {
+        Ast::Located(span, import) => match **import {
+            Ast::Import {
+                module: imported, ..
+            } if imported == module => Some(*span),
+            _ => None,
+        },
+        _ => None,
+    })
+}
+
+/// `span` without the whitespace at either end, if there's anything else
+fn trimmed(text: &str, span: Span) -> Option<Span> {
+    let piece = &text[span.start..span.end];
+    let start = span.start + (piece.len() - piece.trim_start().len());
+    let end = span.end - (piece.len() - piece.trim_end().len());
+    (start < end).then(|| Span::new(start, end))
+}
+
+/// call `f` with every located node, outermost first
+fn visit_located<'a>(node: &'a TypedIr, f: &mut impl FnMut(Span, &'a TypedIr)) {
+    if let TypedIr::Located(span, inner) = node {
+        let mut inner = &**inner;
+        while let TypedIr::Located(_, next) = inner {
+            inner = next;
+        }
+        f(*span, inner);
+    }
+    for child in node.children() {
+        visit_located(child, f);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Binder {
+    /// by a `let`, `fn` or `data` statement at this location
+    Declared(Span),
+    /// by a lambda or a pattern
+    Local,
+    /// by nothing in the program, so it comes from the environment
+    Unbound,
+}
+
+/// call `f` with the location of every identifier, along with what binds it
+/// - `declaration` - location of the declaration `node` belongs to, if any
+/// - `scope` - names bound around `node`, innermost last
+fn resolve_names(
+    node: &TypedIr,
+    declaration: Option<Span>,
+    scope: &mut Vec<(Name, Binder)>,
+    f: &mut impl FnMut(Span, Name, Binder),
+) {
+    use TypedIr::*;
+    match node {
+        Located(span, inner) => {
+            if let Id(name, _) = **inner {
+                let binder = scope
+                    .iter()
+                    .rev()
+                    .find(|(bound, _)| *bound == name)
+                    .map_or(Binder::Unbound, |(_, binder)| *binder);
+                f(*span, name, binder);
+            } else {
+                resolve_names(inner, Some(*span), scope, f);
+            }
+        }
+        Let {
+            e1, binding, e2, ..
+        } => {
+            resolve_names(e1, None, scope, f);
+            let binder = declaration.map_or(Binder::Local, Binder::Declared);
+            scope.push((binding, binder));
+            resolve_names(e2, None, scope, f);
+            scope.pop();
+        }
+        Lam { binding, body, .. } => {
+            scope.push((binding, Binder::Local));
+            resolve_names(body, None, scope, f);
+            scope.pop();
+        }
+        Data { constructors, e2 } => {
+            let binder = declaration.map_or(Binder::Local, Binder::Declared);
+            let depth = scope.len();
+            scope.extend(constructors.iter().map(|(name, _)| (*name, binder)));
+            resolve_names(e2, None, scope, f);
+            scope.truncate(depth);
+        }
+        Match {
+            scrutinee, arms, ..
+        } => {
+            resolve_names(scrutinee, None, scope, f);
+            for (pattern, arm) in arms {
+                let depth = scope.len();
+                scope.extend(
+                    pattern
+                        .bound_names()
+                        .into_iter()
+                        .map(|n| (n, Binder::Local)),
+                );
+                resolve_names(arm, None, scope, f);
+                scope.truncate(depth);
+            }
+        }
+        _ => {
+            for child in node.children() {
+                resolve_names(child, None, scope, f);
+            }
+        }
+    }
+}
+
+/// where `name` is first mentioned as a whole word within `declaration`
+fn name_span(text: &str, declaration: Span, name: &str) -> Span {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_' || c == '\'';
+    let source = &text[declaration.start..declaration.end];
+    source
+        .match_indices(name)
+        .find(|(i, _)| {
+            let before = source[..*i].chars().next_back();
+            let after = source[i + name.len()..].chars().next();
+            !before.is_some_and(is_ident) && !after.is_some_and(is_ident)
+        })
+        .map_or(declaration, |(i, _)| {
+            let start = declaration.start + i;
+            Span::new(start, start + name.len())
+        })
+}
+
+/// LSP positions count UTF-16 code units within a line
+fn position(text: &str, offset: usize) -> Json {
+    let before = &text[..offset.min(text.len())];
+    let line = before.matches('\n').count();
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let character = before[line_start..].encode_utf16().count();
+    Json::object([("line", line.into()), ("character", character.into())])
+}
+
+fn range(text: &str, span: Span) -> Json {
+    Json::object([
+        ("start", position(text, span.start)),
+        ("end", position(text, span.end)),
+    ])
+}
+
+/// byte offset of an LSP position
+fn offset(text: &str, line: usize, character: usize) -> usize {
+    let line_start = if line == 0 {
+        0
+    } else {
+        text.match_indices('\n')
+            .nth(line - 1)
+            .map_or(text.len(), |(i, _)| i + 1)
+    };
+    let mut units = 0;
+    for (i, c) in text[line_start..].char_indices() {
+        if units >= character || c == '\n' {
+            return line_start + i;
+        }
+        units += c.len_utf16();
+    }
+    text.len()
+}
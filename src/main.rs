@@ -1,88 +1,235 @@
 #![warn(clippy::pedantic)]
 
-use std::collections::HashSet;
+use std::{
+    io::{BufRead, Write},
+    path::Path,
+    process::ExitCode,
+};
 
-use heeren_hage_swierstra::{fresh_type_id, infer_type, Ast, Environment, Type};
+use heeren_hage_swierstra::{
+    check_discarded_values, default_environment, generate_constraints, parse, parse_environment,
+    scheme_to_string, solve_traced, type_error_slice, ApplySubst, Environment, ModuleLoader,
+    Session, Span,
+};
 
-/// create the AST to test with
-fn mk_ast() -> Ast {
-    // fn f0 x =
-    //   fn f1 x =
-    //     x = x + 1
-    //     x = x + (len "test")
-    //   f1 x
-    // print "test"
-    // print (f0 10)
-    let f1 = Ast::Fn {
-        fn_name: "f1",
-        parameter: "x",
-        body: Ast::Add(
-            Ast::Id("x").into(),
-            Ast::Add(
-                Ast::LiteralInt(1).into(),
-                Ast::App {
-                    e1: Ast::Id("len").into(),
-                    e2: Ast::LiteralStr("test").into(),
+const USAGE: &str = "usage: heeren_hage_swierstra [--prelude FILE] [--dump-ast] [--dump-ir] [--dump-constraints] [--dump-typed] [--trace] [--trace-json] [--dot] [--warn-discarded] FILE...
+       heeren_hage_swierstra [--prelude FILE] [--repl]";
+
+/// intermediate results and solver steps to print on the way to the
+/// top-level schemes
+#[derive(Default)]
+#[allow(clippy::struct_excessive_bools)] // independent command-line flags
+struct Dumps {
+    ast: bool,
+    ir: bool,
+    constraints: bool,
+    typed: bool,
+    trace: bool,
+    trace_json: bool,
+    /// print DOT graphs instead of ASCII trees and constraint lists
+    dot: bool,
+    /// warn about statements whose values are thrown away
+    discarded: bool,
+}
+
+fn main() -> ExitCode {
+    let mut dumps = Dumps::default();
+    let mut paths = Vec::new();
+    let mut prelude = None;
+    let mut repl_requested = false;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dump-ast" => dumps.ast = true,
+            "--dump-ir" => dumps.ir = true,
+            "--dump-constraints" => dumps.constraints = true,
+            "--dump-typed" => dumps.typed = true,
+            "--trace" => dumps.trace = true,
+            "--trace-json" => dumps.trace_json = true,
+            "--dot" => dumps.dot = true,
+            "--warn-discarded" => dumps.discarded = true,
+            "--prelude" => {
+                prelude = args.next();
+                if prelude.is_none() {
+                    eprintln!("`--prelude` needs a file\n{USAGE}");
+                    return ExitCode::from(2);
                 }
-                .into(),
-            )
-            .into(),
-        )
-        .into(),
-    };
-    let f0: Ast = Ast::Fn {
-        fn_name: "f0",
-        parameter: "x",
-        body: Ast::Do(vec![
-            f1,
-            Ast::App {
-                e1: Ast::Id("f1").into(),
-                e2: Ast::Id("x").into(),
-            },
-        ])
-        .into(),
-    };
-    Ast::Do(vec![
-        f0,
-        Ast::App {
-            e1: Ast::Id("print").into(),
-            e2: Ast::LiteralStr("test").into(),
-        },
-        Ast::App {
-            e1: Ast::Id("print").into(),
-            e2: Ast::App {
-                e1: Ast::Id("f0").into(),
-                e2: Ast::LiteralInt(10).into(),
             }
-            .into(),
+            "--repl" => repl_requested = true,
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return ExitCode::SUCCESS;
+            }
+            flag if flag.starts_with('-') => {
+                eprintln!("unknown option `{flag}`\n{USAGE}");
+                return ExitCode::from(2);
+            }
+            _ => paths.push(arg),
+        }
+    }
+    let environment = match prelude {
+        Some(path) => match read_prelude(&path) {
+            Ok(environment) => environment,
+            Err(diagnostic) => {
+                eprintln!("{diagnostic}");
+                return ExitCode::FAILURE;
+            }
         },
-    ])
+        None => default_environment(),
+    };
+    if repl_requested || paths.is_empty() {
+        return repl(environment);
+    }
+
+    let mut failed = false;
+    for path in &paths {
+        if let Err(diagnostic) = check_file(path, &environment, &dumps) {
+            eprintln!("{diagnostic}");
+            failed = true;
+        }
+    }
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
 }
 
-/// create an environment to test with
-fn mk_env() -> Environment {
-    let print_ty_id = fresh_type_id();
-    [
-        (
-            "len",
-            (HashSet::new(), Type::F(Type::Str.into(), Type::Int.into())),
-        ),
-        (
-            "print",
-            (
-                HashSet::from([print_ty_id]),
-                Type::F(Type::Unknown(print_ty_id).into(), Type::Nothing.into()),
-            ),
-        ),
-    ]
-    .into()
+/// declarations to use instead of the built-ins
+fn read_prelude(path: &str) -> Result<Environment, String> {
+    let source = std::fs::read_to_string(path).map_err(|e| format!("{path}: error: {e}"))?;
+    parse_environment(&source).map_err(|e| {
+        let (line, col) = e.span.line_col(&source);
+        format!("{path}:{line}:{col}: error: {e}")
+    })
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let env = mk_env();
-    let ast = mk_ast();
-    let ir = ast.desugar();
-    let (_, typed_tree) = infer_type(&env, ir);
-    println!("{}", typed_tree.to_string()?);
+/// typecheck one source file, printing the scheme of each top-level binding.
+/// Modules it imports are looked for next to it
+fn check_file(path: &str, environment: &Environment, dumps: &Dumps) -> Result<(), String> {
+    let source = std::fs::read_to_string(path).map_err(|e| format!("{path}: error: {e}"))?;
+    let error = |span: Option<Span>, message: &dyn std::fmt::Display| match span {
+        Some(span) => {
+            let (line, col) = span.line_col(&source);
+            format!("{path}:{line}:{col}: error: {message}")
+        }
+        None => format!("{path}: error: {message}"),
+    };
+
+    let ast = parse(&source).map_err(|e| error(Some(e.span), &e))?;
+    if dumps.ast {
+        if dumps.dot {
+            print!("{}", ast.to_dot());
+        } else {
+            ast.display_tree();
+        }
+    }
+    let file = Path::new(path);
+    let directory = file.parent().unwrap_or(Path::new("."));
+    let mut modules = ModuleLoader::in_directory(directory, environment.clone());
+    let module = file
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or(path);
+    let environment = modules
+        .environment_for(module, &ast)
+        .map_err(|e| error(None, &e))?;
+    let ir = ast.desugar_program().map_err(|e| error(None, &e))?;
+    if dumps.ir {
+        if dumps.dot {
+            print!("{}", ir.to_dot());
+        } else {
+            ir.display_tree();
+        }
+    }
+    let (constraints, mut typed) =
+        generate_constraints(&environment, ir.clone()).map_err(|e| error(None, &e))?;
+    if dumps.constraints {
+        if dumps.dot {
+            print!("{}", constraints.to_dot());
+        } else {
+            println!("{constraints}");
+        }
+    }
+    let substitutions = solve_traced(constraints, &mut |event| {
+        if dumps.trace {
+            println!("{event}");
+        }
+        if dumps.trace_json {
+            println!("{}", event.to_json());
+        }
+    })
+    .map_err(|e| {
+        // at the first part of the program the error comes from
+        let slice = type_error_slice(&environment, ir).unwrap_or_default();
+        let blamed = slice.into_iter().find_map(|span| {
+            let text = &source[span.start..span.end];
+            let start = span.start + text.find(|c: char| !c.is_whitespace())?;
+            Some(Span::new(start, span.end))
+        });
+        error(blamed, &e)
+    })?;
+    typed.apply_subst(&substitutions);
+    let holes = typed.holes(&environment);
+    if !holes.is_empty() {
+        let holes = holes.iter().map(|hole| error(hole.span, hole));
+        return Err(holes.collect::<Vec<_>>().join("\n"));
+    }
+    if dumps.discarded {
+        for value in check_discarded_values(&typed) {
+            let at = value.span.map(|span| {
+                let (line, col) = span.line_col(&source);
+                format!("{line}:{col}:")
+            });
+            let at = at.unwrap_or_default();
+            eprintln!("{path}:{at} warning: discarded value of type {}", value.ty);
+        }
+    }
+    if dumps.typed {
+        if dumps.dot {
+            print!("{}", typed.to_dot());
+        } else {
+            println!("{}", typed.to_string().map_err(|e| error(None, &e))?);
+        }
+    }
+    for (name, scheme) in typed.top_level_bindings(&environment) {
+        println!("{name} : {}", scheme_to_string(&scheme));
+    }
     Ok(())
 }
+
+/// read inputs from stdin one line at a time, until `:quit` or end of input
+fn repl(environment: Environment) -> ExitCode {
+    let mut session = Session::with_environment(environment);
+    let mut lines = std::io::stdin().lock().lines();
+    loop {
+        print!("> ");
+        if std::io::stdout().flush().is_err() {
+            return ExitCode::FAILURE;
+        }
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            Some(Err(e)) => {
+                eprintln!("error: {e}");
+                return ExitCode::FAILURE;
+            }
+            None => return ExitCode::SUCCESS,
+        };
+        match line.trim() {
+            "" => (),
+            ":quit" | ":q" => return ExitCode::SUCCESS,
+            ":help" | ":h" => println!(
+                ":type EXPR         show the type of EXPR\n\
+                 :constraints EXPR  show the constraints generated for EXPR\n\
+                 :env               show every binding in scope\n\
+                 :reset             forget every binding made so far\n\
+                 :quit              exit"
+            ),
+            input => match session.run(input) {
+                Ok(out) if out.is_empty() => (),
+                Ok(out) => println!("{out}"),
+                Err(e) => eprintln!("error: {e}"),
+            },
+        }
+    }
+}
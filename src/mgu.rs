@@ -1,20 +1,53 @@
-use crate::{compose, Substitutions, Type};
+use crate::{
+    compose, prelude::expand_aliases, ApplySubst, FreeVars, Substitutions, Type, TypeError,
+};
 
 /// find the most general unifier for the two types
-/// # Panics
-/// Panics if it encounters a pair of types I didn't anticipate
-#[must_use]
-pub fn mgu(t1: &Type, t2: &Type) -> Substitutions {
+/// # Errors
+/// Returns `TypeError::Mismatch` if the types cannot be unified. When an
+/// alias is involved, the error names the alias rather than its expansion,
+/// unless the two would then read the same, as for an alias and one it
+/// shadows.
+/// Returns `TypeError::InfiniteType` if a type variable would have to
+/// contain itself
+pub fn mgu(t1: &Type, t2: &Type) -> Result<Substitutions, TypeError> {
     use Type::*;
     match (t1, t2) {
-        (Int, Int) | (Str, Str) | (Nothing, Nothing) => Substitutions::new(),
-        (F(param1, result1), F(param2, result2)) => {
-            let s1 = mgu(param1, param2);
-            let s2 = mgu(result1, result2);
-            compose(s1, s2)
+        (Int, Int) | (Str, Str) | (Nothing, Nothing) => Ok(Substitutions::new()),
+        (Con(name1), Con(name2)) if name1 == name2 => Ok(Substitutions::new()),
+        (F(param1, result1), F(param2, result2)) => mgu_pair((param1, result1), (param2, result2)),
+        // `f a` against `List Int` unifies `f` with `List` and `a` with `Int`
+        (App(constructor1, arg1), App(constructor2, arg2)) => {
+            mgu_pair((constructor1, arg1), (constructor2, arg2))
         }
-        (Unknown(id1), Unknown(id2)) if id1 == id2 => Substitutions::new(),
-        (Unknown(id), known) | (known, Unknown(id)) => [(*id, known.clone())].into_iter().collect(),
-        _ => panic!("Unable to unify types: {t1:?} {t2:?}"),
+        (Unknown(id1), Unknown(id2)) if id1 == id2 => Ok(Substitutions::new()),
+        // `t1 = t1 -> t2` has no solution, except an infinitely big type
+        (Unknown(id), known) | (known, Unknown(id)) if known.free_vars().contains(id) => {
+            Err(TypeError::InfiniteType(Unknown(*id), known.clone()))
+        }
+        (Unknown(id), known) | (known, Unknown(id)) => {
+            Ok([(*id, known.clone())].into_iter().collect())
+        }
+        (Alias(..), _) | (_, Alias(..)) => mgu(t1.expand(), t2.expand()).map_err(|_| {
+            if t1.to_string() == t2.to_string() {
+                TypeError::Mismatch(expand_aliases(t1), expand_aliases(t2))
+            } else {
+                TypeError::Mismatch(t1.clone(), t2.clone())
+            }
+        }),
+        _ => Err(TypeError::Mismatch(t1.clone(), t2.clone())),
     }
 }
+
+/// unify two pairs of types, applying what was learned from the first
+/// components before unifying the second
+fn mgu_pair(
+    (left1, right1): (&Type, &Type),
+    (left2, right2): (&Type, &Type),
+) -> Result<Substitutions, TypeError> {
+    let s1 = mgu(left1, left2)?;
+    let mut remaining = (right1.clone(), right2.clone());
+    remaining.apply_subst(&s1);
+    let s2 = mgu(&remaining.0, &remaining.1)?;
+    Ok(compose(s2, s1))
+}
@@ -0,0 +1,411 @@
+//! Modules, one per source file.
+//!
+//! `import Geometry` makes everything `Geometry.hhs` exports available as
+//! `Geometry.area` and so on, and `import Geometry (area, Circle)` also
+//! brings the listed names into scope unqualified. A module exports every
+//! top-level binding, including data constructors, unless it has `export`
+//! statements listing the ones to export.
+//!
+//! Importers only see a module's generalized schemes, so each module is
+//! inferred once, however many modules import it. Those schemes can be
+//! kept in interface files, and then a module is only inferred again when
+//! its source, or the interface of a module it imports, changes. The types a
+//! module declares are qualified by its name in what it exports, so `data T`
+//! in two modules declares two different types, `A.T` and `B.T`. Aliases
+//! are expanded in what a module exports, as they are in interface files.
+//! Annotations can only name types declared in the same module.
+
+use std::{collections::HashMap, fmt, io, path::PathBuf};
+
+use crate::{
+    environment_to_string, infer_type,
+    interface::{content_hash, Interface},
+    intern, parse,
+    prelude::expand_aliases,
+    Ast, Environment, FreeVars, Name, ParseError, Type, TypeError, TypedIr,
+};
+
+/// What a module makes available to the modules that import it
+#[derive(Debug, Clone)]
+pub struct Module {
+    pub name: Name,
+    /// schemes of the exported names, unqualified. Types the module declares
+    /// are qualified by its name
+    pub exports: Environment,
+    /// identifies the module's source, along with the interfaces of the
+    /// modules it imports
+    pub hash: u64,
+}
+
+/// gets the source of a module from its name
+type ReadModule = Box<dyn FnMut(&str) -> io::Result<String>>;
+/// gets the text of a module's interface file, if there is one
+type ReadInterface = Box<dyn FnMut(&str) -> Option<String>>;
+/// saves a module's interface file
+type WriteInterface = Box<dyn FnMut(&str, &str)>;
+
+/// Finds, checks and remembers modules, by name
+pub struct ModuleLoader {
+    environment: Environment,
+    /// `content_hash` of `environment`, so that interfaces inferred with a
+    /// different prelude aren't used
+    environment_hash: u64,
+    read: ReadModule,
+    interfaces: Option<(ReadInterface, WriteInterface)>,
+    modules: HashMap<Name, Module>,
+    /// modules whose imports are being loaded, outermost first
+    loading: Vec<Name>,
+}
+
+/// Reasons a module can fail to load
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModuleError {
+    /// the module's source couldn't be read
+    Read {
+        module: Name,
+        message: String,
+    },
+    Parse {
+        module: Name,
+        error: ParseError,
+    },
+    Type {
+        module: Name,
+        error: TypeError,
+    },
+    /// modules that import each other, starting and ending with the same one
+    Cycle(Vec<Name>),
+    /// an `export` statement names something the module doesn't bind
+    UnknownExport {
+        module: Name,
+        name: Name,
+    },
+    /// an `import` lists something the module doesn't export
+    NotExported {
+        module: Name,
+        name: Name,
+    },
+    /// an exported binding wasn't generalized, and its type has variables
+    /// that uses of it decide. Each importer would decide them differently
+    WeakExport {
+        module: Name,
+        name: Name,
+    },
+}
+
+impl ModuleLoader {
+    /// load modules using `read`, which gets a module's source from its name.
+    /// Every module starts with `environment` in scope
+    pub fn new(
+        environment: Environment,
+        read: impl FnMut(&str) -> io::Result<String> + 'static,
+    ) -> Self {
+        Self {
+            environment_hash: content_hash(&environment_to_string(&environment), []),
+            environment,
+            read: Box::new(read),
+            interfaces: None,
+            modules: HashMap::new(),
+            loading: Vec::new(),
+        }
+    }
+
+    /// load modules from the `.hhs` files in `directory`, with `environment`
+    /// in scope. Interface files are kept next to them, as `.hhsi` files
+    pub fn in_directory(directory: impl Into<PathBuf>, environment: Environment) -> Self {
+        let directory = directory.into();
+        let sources = directory.clone();
+        let interfaces = directory.clone();
+        Self::new(environment, move |module| {
+            std::fs::read_to_string(sources.join(format!("{module}.hhs")))
+        })
+        .with_interfaces(
+            move |module| std::fs::read_to_string(interfaces.join(format!("{module}.hhsi"))).ok(),
+            move |module, interface| {
+                // the interface is only a cache, so failing to save it
+                // just means inferring the module next time too
+                let _ = std::fs::write(directory.join(format!("{module}.hhsi")), interface);
+            },
+        )
+    }
+
+    /// use interface files read by `read` instead of inferring modules that
+    /// haven't changed, and save them with `write` after inferring ones that
+    /// have
+    #[must_use]
+    pub fn with_interfaces(
+        mut self,
+        read: impl FnMut(&str) -> Option<String> + 'static,
+        write: impl FnMut(&str, &str) + 'static,
+    ) -> Self {
+        self.interfaces = Some((Box::new(read), Box::new(write)));
+        self
+    }
+
+    /// the module called `name`, checking it and the modules it imports if
+    /// that hasn't been done yet
+    /// # Errors
+    /// Returns a `ModuleError` if it, or a module it imports, can't be read
+    /// or doesn't typecheck, or if its imports form a cycle
+    pub fn load(&mut self, name: &str) -> Result<&Module, ModuleError> {
+        let name = intern(name);
+        if !self.modules.contains_key(name) {
+            if let Some(i) = self.loading.iter().position(|loading| *loading == name) {
+                let mut cycle = self.loading[i..].to_vec();
+                cycle.push(name);
+                return Err(ModuleError::Cycle(cycle));
+            }
+            let source = (self.read)(name).map_err(|e| ModuleError::Read {
+                module: name,
+                message: e.to_string(),
+            })?;
+            let ast = parse(&source).map_err(|error| ModuleError::Parse {
+                module: name,
+                error,
+            })?;
+            let environment = self.environment_for(name, &ast)?;
+            let hash = self.hash(&source, &ast);
+            let interface = (self.interfaces.as_mut())
+                .and_then(|(read, _)| read(name))
+                .and_then(|text| Interface::parse(&text).ok())
+                .filter(|interface| interface.hash == hash);
+            let module = if let Some(interface) = interface {
+                Module {
+                    name,
+                    exports: interface.exports,
+                    hash,
+                }
+            } else {
+                let (module, _) = infer(name, ast, &environment, hash)?;
+                if let Some((_, write)) = &mut self.interfaces {
+                    let interface = Interface::new(hash, module.exports.clone());
+                    write(name, &interface.to_string());
+                }
+                module
+            };
+            self.modules.insert(name, module);
+        }
+        Ok(&self.modules[name])
+    }
+
+    /// check the source of the module called `name`, returning what it
+    /// exports along with its typed tree
+    /// # Errors
+    /// Returns a `ModuleError` if it doesn't typecheck, or if loading one of
+    /// its imports fails
+    pub fn check(&mut self, name: &str, source: &str) -> Result<(Module, TypedIr), ModuleError> {
+        let name = intern(name);
+        let ast = parse(source).map_err(|error| ModuleError::Parse {
+            module: name,
+            error,
+        })?;
+        let environment = self.environment_for(name, &ast)?;
+        let hash = self.hash(source, &ast);
+        infer(name, ast, &environment, hash)
+    }
+
+    /// `content_hash` of a module, whose imports must already be loaded
+    fn hash(&self, source: &str, ast: &Ast) -> u64 {
+        let imports = imports(ast)
+            .into_iter()
+            .map(|(module, _)| self.modules[module].hash);
+        content_hash(
+            source,
+            std::iter::once(self.environment_hash).chain(imports),
+        )
+    }
+
+    /// what's in scope for the module called `name`, whose syntax tree is
+    /// `ast`: the loader's environment, along with whatever it imports
+    /// # Errors
+    /// Returns a `ModuleError` if loading one of the imports fails, or if an
+    /// import lists a name its module doesn't export
+    pub fn environment_for(&mut self, name: &str, ast: &Ast) -> Result<Environment, ModuleError> {
+        let mut environment = self.environment.clone();
+        self.loading.push(intern(name));
+        let imported = imports(ast).into_iter().try_for_each(|(module, names)| {
+            let loaded = self.load(module)?;
+            for (export, scheme) in &loaded.exports {
+                environment.insert(intern(&format!("{module}.{export}")), scheme.clone());
+            }
+            for imported in names {
+                let scheme = loaded
+                    .exports
+                    .get(imported)
+                    .ok_or(ModuleError::NotExported {
+                        module,
+                        name: imported,
+                    })?;
+                environment.insert(imported, scheme.clone());
+            }
+            Ok(())
+        });
+        self.loading.pop();
+        imported.map(|()| environment)
+    }
+}
+
+/// infer a module whose imports are loaded into `environment`
+fn infer(
+    name: Name,
+    ast: Ast,
+    environment: &Environment,
+    hash: u64,
+) -> Result<(Module, TypedIr), ModuleError> {
+    let exported = exports(&ast);
+    let declared = declared_types(&ast);
+    let type_error = |error| ModuleError::Type {
+        module: name,
+        error,
+    };
+    let (_, typed) =
+        infer_type(environment, ast.desugar_program().map_err(type_error)?).map_err(type_error)?;
+    let bindings = typed
+        .top_level_bindings(environment)
+        .into_iter()
+        .collect::<Environment>();
+    let exports = match exported {
+        Some(names) => names
+            .into_iter()
+            .map(|export| match bindings.get(export) {
+                Some(scheme) => Ok((export, scheme.clone())),
+                None => Err(ModuleError::UnknownExport {
+                    module: name,
+                    name: export,
+                }),
+            })
+            .collect::<Result<_, _>>()?,
+        None => bindings,
+    };
+    let weak = (exports.iter())
+        .filter(|(_, (quantified, ty))| !ty.free_vars().is_subset(quantified))
+        .map(|(export, _)| *export)
+        .min();
+    if let Some(export) = weak {
+        return Err(ModuleError::WeakExport {
+            module: name,
+            name: export,
+        });
+    }
+    // with aliases expanded, as an interface file has them
+    let exports = (exports.into_iter())
+        .map(|(export, (quantified, ty))| {
+            let ty = qualified(&expand_aliases(&ty), name, &declared);
+            (export, (quantified, ty))
+        })
+        .collect();
+    Ok((
+        Module {
+            name,
+            exports,
+            hash,
+        },
+        typed,
+    ))
+}
+
+/// the top-level statements of a program
+fn statements(ast: &Ast) -> &[Ast] {
+    match ast {
+        Ast::Located(_, inner) => statements(inner),
+        Ast::Do(statements) => statements,
+        statement => std::slice::from_ref(statement),
+    }
+}
+
+fn unlocated(ast: &Ast) -> &Ast {
+    match ast {
+        Ast::Located(_, inner) => unlocated(inner),
+        ast => ast,
+    }
+}
+
+/// modules imported by a program, with the names to import unqualified
+fn imports(ast: &Ast) -> Vec<(Name, Vec<Name>)> {
+    (statements(ast).iter())
+        .filter_map(|statement| match unlocated(statement) {
+            Ast::Import { module, names } => Some((*module, names.clone())),
+            _ => None,
+        })
+        .collect()
+}
+
+/// names listed by a program's `export` statements, if it has any
+fn exports(ast: &Ast) -> Option<Vec<Name>> {
+    let mut exports = None;
+    for statement in statements(ast) {
+        if let Ast::Export(names) = unlocated(statement) {
+            exports
+                .get_or_insert_with(Vec::new)
+                .extend(names.iter().copied());
+        }
+    }
+    exports
+}
+
+/// names of the data types a program's top-level statements declare
+fn declared_types(ast: &Ast) -> Vec<Name> {
+    (statements(ast).iter())
+        .filter_map(|statement| match unlocated(statement) {
+            Ast::Data { name, .. } => Some(*name),
+            _ => None,
+        })
+        .collect()
+}
+
+/// `ty` with the types `module` declares named `module.T`
+fn qualified(ty: &Type, module: Name, declared: &[Name]) -> Type {
+    let qualify = |name: Name| {
+        if declared.contains(&name) {
+            intern(&format!("{module}.{name}"))
+        } else {
+            name
+        }
+    };
+    match ty {
+        Type::Con(name) => Type::Con(qualify(name)),
+        Type::Alias(name, expansion) => {
+            Type::Alias(qualify(name), qualified(expansion, module, declared).into())
+        }
+        Type::F(t1, t2) => Type::F(
+            qualified(t1, module, declared).into(),
+            qualified(t2, module, declared).into(),
+        ),
+        Type::App(t1, t2) => Type::App(
+            qualified(t1, module, declared).into(),
+            qualified(t2, module, declared).into(),
+        ),
+        Type::Int | Type::Str | Type::Nothing | Type::Unknown(_) => ty.clone(),
+    }
+}
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModuleError::Read { module, message } => {
+                write!(f, "can't read module `{module}`: {message}")
+            }
+            ModuleError::Parse { module, error } => write!(f, "in module `{module}`: {error}"),
+            ModuleError::Type { module, error } => write!(f, "in module `{module}`: {error}"),
+            ModuleError::Cycle(modules) => {
+                write!(f, "modules import each other: {}", modules.join(" -> "))
+            }
+            ModuleError::UnknownExport { module, name } => {
+                write!(
+                    f,
+                    "module `{module}` exports `{name}`, which it doesn't define"
+                )
+            }
+            ModuleError::NotExported { module, name } => {
+                write!(f, "module `{module}` doesn't export `{name}`")
+            }
+            ModuleError::WeakExport { module, name } => write!(
+                f,
+                "module `{module}` exports `{name}`, whose type isn't fully known \
+                 until it's used"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ModuleError {}
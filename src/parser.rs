@@ -0,0 +1,718 @@
+//! Source text to `Ast`.
+//!
+//! ```text
+//! program   := stmt (sep stmt)*                  sep is `;` or a newline
+//! stmt      := 'let' NAME '=' expr               scoped over the following statements
+//!            | 'fn' NAME NAME+ '=' expr
+//!            | 'type' NAME '=' type
+//!            | 'data' NAME NAME* '=' ctor ('|' ctor)*
+//!            | 'import' NAME ('(' NAME (',' NAME)* ')')?
+//!            | 'export' NAME (',' NAME)*
+//!            | expr
+//! ctor      := NAME type_atom*
+//! expr      := '\' NAME+ '->' expr
+//!            | 'let' NAME '=' expr (',' NAME '=' expr)* 'in' expr
+//!            | 'match' expr '{' pattern '->' expr (sep pattern '->' expr)* '}'
+//!            | 'do' '{' stmt (sep stmt)* '}'
+//!            | sum (':=' expr)?
+//! sum       := app ('+' app)*
+//! app       := prefix prefix*
+//! prefix    := '!' prefix | atom
+//! atom      := INT | STRING | NAME | '(' expr (':' type)? ')' | '[' (expr (',' expr)*)? ']'
+//!            | '_' | '?' NAME                    holes, for expressions yet to be written
+//! pattern   := '_' | NAME | '[' ']' | '(' pattern ')' | pattern '::' pattern
+//! type      := type_atom+ ('->' type)?
+//! type_atom := NAME | '(' type ')'
+//! ```
+//!
+//! Preludes and interface files declare the schemes of names instead, one
+//! per statement:
+//!
+//! ```text
+//! declaration := (NAME | '!' | ':=') ':' ('forall' NAME+ '.')? type
+//! ```
+//!
+//! Newlines inside parentheses and brackets, and after an operator, don't
+//! end a statement. `--` starts a comment. A capitalized name directly
+//! followed by `.` and another name, like `List.map`, is one qualified NAME.
+
+use std::fmt;
+
+use crate::{intern, Ast, Name, Pattern, Span, TypeExpr};
+
+/// Problem found while parsing, and where
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub span: Span,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// parse a whole program. The result is a `Do` block of its statements
+/// # Errors
+/// Returns a `ParseError` for the first piece of text that doesn't fit the grammar
+pub fn parse(source: &str) -> Result<Ast, ParseError> {
+    let mut parser = Parser {
+        tokens: lex(source)?,
+        pos: 0,
+    };
+    let stmts = parser.statements(&Token::Eof)?;
+    Ok(Ast::Located(
+        Span::new(0, source.len()),
+        Ast::Do(stmts).into(),
+    ))
+}
+
+/// parse a single expression, with nothing after it
+/// # Errors
+/// Returns a `ParseError` for the first piece of text that doesn't fit the grammar
+pub fn parse_expr(source: &str) -> Result<Ast, ParseError> {
+    let mut parser = Parser {
+        tokens: lex(source)?,
+        pos: 0,
+    };
+    parser.skip_separators();
+    let expr = parser.expr()?;
+    parser.skip_separators();
+    parser.expect(&Token::Eof)?;
+    Ok(expr)
+}
+
+/// `name : forall a b. ty`, the scheme of a name
+#[derive(Debug, Clone)]
+pub(crate) struct Declaration {
+    pub name: Name,
+    pub span: Span,
+    /// names of the quantified type variables
+    pub quantified: Vec<Name>,
+    pub ty: TypeExpr,
+}
+
+/// parse a list of declarations
+pub(crate) fn parse_declarations(source: &str) -> Result<Vec<Declaration>, ParseError> {
+    let mut parser = Parser {
+        tokens: lex(source)?,
+        pos: 0,
+    };
+    let mut declarations = Vec::new();
+    parser.skip_separators();
+    while !parser.eat(&Token::Eof) {
+        declarations.push(parser.declaration()?);
+        if parser.peek() != &Token::Eof {
+            if !matches!(parser.peek(), Token::Newline | Token::Sym(";")) {
+                return Err(parser.unexpected("`;` or a new line"));
+            }
+            parser.skip_separators();
+        }
+    }
+    Ok(declarations)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Int(i64),
+    Str(&'static str),
+    Name(&'static str),
+    Keyword(&'static str),
+    Sym(&'static str),
+    Newline,
+    Eof,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Int(i) => write!(f, "`{i}`"),
+            Token::Str(s) => write!(f, "{s:?}"),
+            Token::Name(s) | Token::Keyword(s) | Token::Sym(s) => write!(f, "`{s}`"),
+            Token::Newline => write!(f, "end of line"),
+            Token::Eof => write!(f, "end of input"),
+        }
+    }
+}
+
+const KEYWORDS: [&str; 9] = [
+    "let", "in", "fn", "type", "data", "match", "do", "import", "export",
+];
+
+/// longest first, so `:=` isn't read as `:` then `=`
+const SYMBOLS: [&str; 19] = [
+    "->", ":=", "::", "(", ")", "[", "]", "{", "}", ",", ";", "=", "\\", "+", ":", "!", "|", ".",
+    "?",
+];
+
+/// symbols after which a newline doesn't end the statement
+const CONTINUES: [&str; 9] = ["->", ":=", "::", ",", "=", "\\", "+", ":", "|"];
+
+fn lex(source: &str) -> Result<Vec<(Token, Span)>, ParseError> {
+    let mut tokens = Vec::new();
+    // newlines are insignificant inside `(` and `[`
+    let mut depth = 0usize;
+    let mut chars = source.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c == '\n' {
+            chars.next();
+            let continued = matches!(
+                tokens.last(),
+                Some((Token::Sym(s), _)) if CONTINUES.contains(s)
+            );
+            if depth == 0 && !continued {
+                tokens.push((Token::Newline, Span::new(start, start + 1)));
+            }
+        } else if c.is_whitespace() {
+            chars.next();
+        } else if source[start..].starts_with("--") {
+            while chars.next_if(|&(_, c)| c != '\n').is_some() {}
+        } else if c.is_ascii_digit() {
+            let mut end = start;
+            while let Some((i, c)) = chars.next_if(|(_, c)| c.is_ascii_digit()) {
+                end = i + c.len_utf8();
+            }
+            let span = Span::new(start, end);
+            let i = source[start..end].parse().map_err(|_| ParseError {
+                span,
+                message: "integer literal is too large".to_owned(),
+            })?;
+            tokens.push((Token::Int(i), span));
+        } else if c.is_alphabetic() || c == '_' {
+            let mut end = start;
+            while let Some((i, c)) =
+                chars.next_if(|(_, c)| c.is_alphanumeric() || *c == '_' || *c == '\'')
+            {
+                end = i + c.len_utf8();
+            }
+            // qualified by a module name
+            let qualified = c.is_uppercase()
+                && source[end..].starts_with('.')
+                && source[end + 1..].starts_with(|c: char| c.is_alphabetic() || c == '_');
+            if qualified {
+                chars.next();
+                while let Some((i, c)) =
+                    chars.next_if(|(_, c)| c.is_alphanumeric() || *c == '_' || *c == '\'')
+                {
+                    end = i + c.len_utf8();
+                }
+            }
+            let name = &source[start..end];
+            let token = match KEYWORDS.iter().find(|k| **k == name) {
+                Some(keyword) => Token::Keyword(keyword),
+                None => Token::Name(intern(name)),
+            };
+            tokens.push((token, Span::new(start, end)));
+        } else if c == '"' {
+            chars.next();
+            let mut s = String::new();
+            let end = loop {
+                match chars.next() {
+                    Some((i, '"')) => break Some(i + 1),
+                    Some((_, '\\')) => match chars.next() {
+                        Some((_, 'n')) => s.push('\n'),
+                        Some((_, 't')) => s.push('\t'),
+                        Some((_, c @ ('"' | '\\'))) => s.push(c),
+                        Some((i, c)) => {
+                            return Err(ParseError {
+                                span: Span::new(i - 1, i + c.len_utf8()),
+                                message: format!("unknown escape `\\{c}`"),
+                            })
+                        }
+                        None => break None,
+                    },
+                    Some((_, c)) => s.push(c),
+                    None => break None,
+                }
+            };
+            let end = end.ok_or_else(|| ParseError {
+                span: Span::new(start, source.len()),
+                message: "unterminated string literal".to_owned(),
+            })?;
+            tokens.push((Token::Str(intern(&s)), Span::new(start, end)));
+        } else if let Some(sym) = SYMBOLS.iter().find(|s| source[start..].starts_with(**s)) {
+            for _ in 0..sym.len() {
+                chars.next();
+            }
+            match *sym {
+                "(" | "[" => depth += 1,
+                ")" | "]" => depth = depth.saturating_sub(1),
+                _ => (),
+            }
+            tokens.push((Token::Sym(sym), Span::new(start, start + sym.len())));
+        } else {
+            return Err(ParseError {
+                span: Span::new(start, start + c.len_utf8()),
+                message: format!("unexpected character `{c}`"),
+            });
+        }
+    }
+    tokens.push((Token::Eof, Span::new(source.len(), source.len())));
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, Span)>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos].0
+    }
+    fn span(&self) -> Span {
+        self.tokens[self.pos].1
+    }
+    /// end of the most recently consumed token
+    fn last_end(&self) -> usize {
+        self.pos.checked_sub(1).map_or(0, |i| self.tokens[i].1.end)
+    }
+    fn bump(&mut self) -> Token {
+        let token = self.peek().clone();
+        if token != Token::Eof {
+            self.pos += 1;
+        }
+        token
+    }
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == token {
+            self.bump();
+            true
+        } else {
+            false
+        }
+    }
+    fn expect(&mut self, token: &Token) -> Result<Span, ParseError> {
+        let span = self.span();
+        if self.eat(token) {
+            Ok(span)
+        } else {
+            Err(self.unexpected(&token.to_string()))
+        }
+    }
+    fn expect_name(&mut self) -> Result<&'static str, ParseError> {
+        match self.peek() {
+            Token::Name(name) => {
+                let name = *name;
+                self.bump();
+                Ok(name)
+            }
+            _ => Err(self.unexpected("a name")),
+        }
+    }
+    fn unexpected(&self, expected: &str) -> ParseError {
+        ParseError {
+            span: self.span(),
+            message: format!("expected {expected}, found {}", self.peek()),
+        }
+    }
+    fn peek_past_newlines(&self) -> &Token {
+        self.tokens[self.pos..]
+            .iter()
+            .map(|(token, _)| token)
+            .find(|token| **token != Token::Newline)
+            .unwrap_or(&Token::Eof)
+    }
+    fn skip_newlines(&mut self) {
+        while self.eat(&Token::Newline) {}
+    }
+    fn skip_separators(&mut self) {
+        while self.eat(&Token::Newline) || self.eat(&Token::Sym(";")) {}
+    }
+    /// wrap `ast` with the span from `start` to the end of the last token
+    fn located(&self, start: usize, ast: Ast) -> Ast {
+        Ast::Located(Span::new(start, self.last_end()), ast.into())
+    }
+
+    /// statements up to (and including) `end`
+    fn statements(&mut self, end: &Token) -> Result<Vec<Ast>, ParseError> {
+        let mut stmts = Vec::new();
+        self.skip_separators();
+        while !self.eat(end) {
+            stmts.push(self.statement()?);
+            if self.peek() != end {
+                if !matches!(self.peek(), Token::Newline | Token::Sym(";")) {
+                    return Err(self.unexpected(&format!("`;`, a new line or {end}")));
+                }
+                self.skip_separators();
+            }
+        }
+        Ok(stmts)
+    }
+
+    fn statement(&mut self) -> Result<Ast, ParseError> {
+        let start = self.span().start;
+        let stmt = match self.peek() {
+            Token::Keyword("let") => return self.let_(),
+            Token::Keyword("fn") => {
+                self.bump();
+                let fn_name = self.expect_name()?;
+                let mut parameters = vec![self.expect_name()?];
+                while let Token::Name(name) = *self.peek() {
+                    parameters.push(name);
+                    self.bump();
+                }
+                self.expect(&Token::Sym("="))?;
+                Ast::Fn {
+                    fn_name,
+                    parameters,
+                    body: self.expr()?.into(),
+                }
+            }
+            Token::Keyword("type") => {
+                self.bump();
+                let name = self.expect_name()?;
+                self.expect(&Token::Sym("="))?;
+                Ast::TypeAlias {
+                    name,
+                    ty: self.type_()?,
+                }
+            }
+            Token::Keyword("data") => {
+                self.bump();
+                let name = self.expect_name()?;
+                let mut params = Vec::new();
+                while let Token::Name(param) = *self.peek() {
+                    params.push(param);
+                    self.bump();
+                }
+                self.expect(&Token::Sym("="))?;
+                let mut constructors = Vec::new();
+                loop {
+                    let constructor = self.expect_name()?;
+                    let mut fields = Vec::new();
+                    while matches!(self.peek(), Token::Name(_) | Token::Sym("(")) {
+                        fields.push(self.type_atom()?);
+                    }
+                    constructors.push((constructor, fields));
+                    // constructors may start on new lines, but the newline
+                    // after the last one ends the statement
+                    if self.peek_past_newlines() != &Token::Sym("|") {
+                        break;
+                    }
+                    self.skip_newlines();
+                    self.bump();
+                }
+                Ast::Data {
+                    name,
+                    params,
+                    constructors,
+                }
+            }
+            Token::Keyword("import") => {
+                self.bump();
+                let module = self.expect_name()?;
+                let mut names = Vec::new();
+                if self.eat(&Token::Sym("(")) {
+                    names.push(self.expect_name()?);
+                    while self.eat(&Token::Sym(",")) {
+                        names.push(self.expect_name()?);
+                    }
+                    self.expect(&Token::Sym(")"))?;
+                }
+                Ast::Import { module, names }
+            }
+            Token::Keyword("export") => {
+                self.bump();
+                let mut names = vec![self.expect_name()?];
+                while self.eat(&Token::Sym(",")) {
+                    names.push(self.expect_name()?);
+                }
+                Ast::Export(names)
+            }
+            _ => return self.expr(),
+        };
+        Ok(self.located(start, stmt))
+    }
+
+    /// `let` statement, or `let ... in` expression
+    fn let_(&mut self) -> Result<Ast, ParseError> {
+        let start = self.expect(&Token::Keyword("let"))?.start;
+        let mut bindings = Vec::new();
+        loop {
+            let binding = self.expect_name()?;
+            self.expect(&Token::Sym("="))?;
+            bindings.push((binding, self.expr()?));
+            if !self.eat(&Token::Sym(",")) {
+                break;
+            }
+        }
+        if self.eat(&Token::Keyword("in")) {
+            self.skip_newlines();
+            let body = self.expr()?;
+            return Ok(self.located(
+                start,
+                Ast::LetIn {
+                    bindings,
+                    body: body.into(),
+                },
+            ));
+        }
+        match <[_; 1]>::try_from(bindings) {
+            Ok([(binding, e1)]) => Ok(self.located(
+                start,
+                Ast::Let {
+                    e1: e1.into(),
+                    binding,
+                },
+            )),
+            Err(_) => Err(self.unexpected("`in`")),
+        }
+    }
+
+    fn expr(&mut self) -> Result<Ast, ParseError> {
+        let start = self.span().start;
+        let expr = match self.peek() {
+            Token::Sym("\\") => {
+                self.bump();
+                let mut bindings = vec![self.expect_name()?];
+                while let Token::Name(name) = *self.peek() {
+                    bindings.push(name);
+                    self.bump();
+                }
+                self.expect(&Token::Sym("->"))?;
+                Ast::Lam {
+                    bindings,
+                    body: self.expr()?.into(),
+                }
+            }
+            Token::Keyword("let") => {
+                let expr = self.let_()?;
+                if let Ast::Located(_, inner) = &expr {
+                    if matches!(**inner, Ast::Let { .. }) {
+                        return Err(self.unexpected("`in`"));
+                    }
+                }
+                return Ok(expr);
+            }
+            Token::Keyword("match") => {
+                self.bump();
+                let scrutinee = self.sum()?;
+                self.expect(&Token::Sym("{"))?;
+                self.skip_separators();
+                let mut arms = Vec::new();
+                while !self.eat(&Token::Sym("}")) {
+                    let pattern = self.pattern()?;
+                    self.expect(&Token::Sym("->"))?;
+                    arms.push((pattern, self.expr()?));
+                    if self.peek() != &Token::Sym("}") {
+                        if !matches!(self.peek(), Token::Newline | Token::Sym(";")) {
+                            return Err(self.unexpected("`;`, a new line or `}`"));
+                        }
+                        self.skip_separators();
+                    }
+                }
+                Ast::Match {
+                    scrutinee: scrutinee.into(),
+                    arms,
+                }
+            }
+            Token::Keyword("do") => {
+                self.bump();
+                self.expect(&Token::Sym("{"))?;
+                Ast::Do(self.statements(&Token::Sym("}"))?)
+            }
+            _ => {
+                let lhs = self.sum()?;
+                let op = self.span();
+                if !self.eat(&Token::Sym(":=")) {
+                    return Ok(lhs);
+                }
+                let rhs = self.expr()?;
+                let assign = Ast::Located(op, Ast::Id(":=").into());
+                Ast::App {
+                    e1: Ast::App {
+                        e1: assign.into(),
+                        e2: lhs.into(),
+                    }
+                    .into(),
+                    e2: rhs.into(),
+                }
+            }
+        };
+        Ok(self.located(start, expr))
+    }
+
+    fn sum(&mut self) -> Result<Ast, ParseError> {
+        let start = self.span().start;
+        let mut lhs = self.app()?;
+        while self.eat(&Token::Sym("+")) {
+            let rhs = self.app()?;
+            lhs = self.located(start, Ast::Add(lhs.into(), rhs.into()));
+        }
+        Ok(lhs)
+    }
+
+    fn app(&mut self) -> Result<Ast, ParseError> {
+        let start = self.span().start;
+        let mut f = self.prefix()?;
+        while matches!(
+            self.peek(),
+            Token::Int(_) | Token::Str(_) | Token::Name(_) | Token::Sym("(" | "[" | "!" | "?")
+        ) {
+            let arg = self.prefix()?;
+            f = self.located(
+                start,
+                Ast::App {
+                    e1: f.into(),
+                    e2: arg.into(),
+                },
+            );
+        }
+        Ok(f)
+    }
+
+    fn prefix(&mut self) -> Result<Ast, ParseError> {
+        let start = self.span().start;
+        if self.eat(&Token::Sym("!")) {
+            let deref = self.located(start, Ast::Id("!"));
+            let arg = self.prefix()?;
+            Ok(self.located(
+                start,
+                Ast::App {
+                    e1: deref.into(),
+                    e2: arg.into(),
+                },
+            ))
+        } else {
+            self.atom()
+        }
+    }
+
+    fn atom(&mut self) -> Result<Ast, ParseError> {
+        let start = self.span().start;
+        let atom = match self.peek().clone() {
+            Token::Int(i) => {
+                self.bump();
+                Ast::LiteralInt(i)
+            }
+            Token::Str(s) => {
+                self.bump();
+                Ast::LiteralStr(s)
+            }
+            Token::Name("_") => {
+                self.bump();
+                Ast::Hole(None)
+            }
+            Token::Name(name) => {
+                self.bump();
+                Ast::Id(name)
+            }
+            Token::Sym("?") => {
+                self.bump();
+                Ast::Hole(Some(self.expect_name()?))
+            }
+            Token::Sym("(") => {
+                self.bump();
+                let expr = self.expr()?;
+                if self.eat(&Token::Sym(":")) {
+                    let ty = self.type_()?;
+                    self.expect(&Token::Sym(")"))?;
+                    Ast::Annot {
+                        expr: expr.into(),
+                        ty,
+                    }
+                } else {
+                    self.expect(&Token::Sym(")"))?;
+                    return Ok(expr);
+                }
+            }
+            Token::Sym("[") => {
+                self.bump();
+                let mut elements = Vec::new();
+                if !self.eat(&Token::Sym("]")) {
+                    loop {
+                        elements.push(self.expr()?);
+                        if !self.eat(&Token::Sym(",")) {
+                            break;
+                        }
+                    }
+                    self.expect(&Token::Sym("]"))?;
+                }
+                Ast::List(elements)
+            }
+            _ => return Err(self.unexpected("an expression")),
+        };
+        Ok(self.located(start, atom))
+    }
+
+    fn pattern(&mut self) -> Result<Pattern, ParseError> {
+        let head = match *self.peek() {
+            Token::Name("_") => Pattern::Wildcard,
+            Token::Name(name) => Pattern::Var(name),
+            Token::Sym("[") => {
+                self.bump();
+                self.expect(&Token::Sym("]"))?;
+                Pattern::Nil
+            }
+            Token::Sym("(") => {
+                self.bump();
+                let pattern = self.pattern()?;
+                self.expect(&Token::Sym(")"))?;
+                pattern
+            }
+            _ => return Err(self.unexpected("a pattern")),
+        };
+        if matches!(head, Pattern::Wildcard | Pattern::Var(_)) {
+            self.bump();
+        }
+        if self.eat(&Token::Sym("::")) {
+            Ok(Pattern::Cons(head.into(), self.pattern()?.into()))
+        } else {
+            Ok(head)
+        }
+    }
+
+    fn declaration(&mut self) -> Result<Declaration, ParseError> {
+        let span = self.span();
+        // the prefix operators are names as well
+        let name = match *self.peek() {
+            Token::Sym(op @ ("!" | ":=")) => {
+                self.bump();
+                op
+            }
+            _ => self.expect_name()?,
+        };
+        self.expect(&Token::Sym(":"))?;
+        let mut quantified = Vec::new();
+        if self.eat(&Token::Name("forall")) {
+            quantified.push(self.expect_name()?);
+            while let Token::Name(var) = *self.peek() {
+                quantified.push(var);
+                self.bump();
+            }
+            self.expect(&Token::Sym("."))?;
+        }
+        Ok(Declaration {
+            name,
+            span,
+            quantified,
+            ty: self.type_()?,
+        })
+    }
+
+    fn type_(&mut self) -> Result<TypeExpr, ParseError> {
+        let mut ty = self.type_atom()?;
+        while matches!(self.peek(), Token::Name(_) | Token::Sym("(")) {
+            ty = TypeExpr::App(ty.into(), self.type_atom()?.into());
+        }
+        if self.eat(&Token::Sym("->")) {
+            Ok(TypeExpr::F(ty.into(), self.type_()?.into()))
+        } else {
+            Ok(ty)
+        }
+    }
+
+    fn type_atom(&mut self) -> Result<TypeExpr, ParseError> {
+        if self.eat(&Token::Sym("(")) {
+            let ty = self.type_()?;
+            self.expect(&Token::Sym(")"))?;
+            Ok(ty)
+        } else {
+            Ok(TypeExpr::Named(self.expect_name()?))
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use std::fmt;
+
+use crate::Name;
+
+/// Left-hand side of a `match` arm
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Pattern {
+    /// `_`, matches anything
+    Wildcard,
+    /// matches anything and binds it to the name
+    Var(
+        #[cfg_attr(
+            feature = "serde",
+            serde(deserialize_with = "crate::serde_support::name")
+        )]
+        Name,
+    ),
+    /// `[]`, the empty list
+    Nil,
+    /// `head :: tail`, a non-empty list
+    Cons(Box<Pattern>, Box<Pattern>),
+}
+
+impl Pattern {
+    /// the names the pattern binds, left to right
+    #[must_use]
+    pub fn bound_names(&self) -> Vec<Name> {
+        match self {
+            Pattern::Wildcard | Pattern::Nil => Vec::new(),
+            Pattern::Var(name) => vec![name],
+            Pattern::Cons(head, tail) => {
+                let mut names = head.bound_names();
+                names.extend(tail.bound_names());
+                names
+            }
+        }
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Pattern::Wildcard => write!(f, "_"),
+            Pattern::Var(name) => write!(f, "{name}"),
+            Pattern::Nil => write!(f, "[]"),
+            Pattern::Cons(head, tail) if matches!(**head, Pattern::Cons(..)) => {
+                write!(f, "({head}) :: {tail}")
+            }
+            Pattern::Cons(head, tail) => write!(f, "{head} :: {tail}"),
+        }
+    }
+}
@@ -0,0 +1,142 @@
+//! Environments declared in text, like the built-in prelude.
+//!
+//! ```text
+//! -- comments start with two dashes
+//! print : forall a. a -> Nothing
+//! len : Str -> Int
+//! ! : forall a. Ref a -> a
+//! ```
+//!
+//! Each line declares the scheme of one name. Type variables must be
+//! quantified with `forall`, except ones starting with `_`, which stand for
+//! the same unknown type everywhere in the file. Other names are the
+//! built-in types `Int`, `Str` and `Nothing`, or type constructors.
+
+use std::{collections::HashMap, fmt::Write, sync::OnceLock};
+
+use crate::{
+    fresh_type_id, intern, parser::parse_declarations, scheme_to_string, ApplySubst, Environment,
+    Name, ParseError, Scheme, Type, TypeExpr,
+};
+
+/// The built-in functions every program can use, declared in `prelude.hhsi`:
+/// - `print : ∀a. a → Nothing`
+/// - `len : Str → Int`
+/// - `ref : ∀a. a → Ref a` allocates a mutable cell
+/// - `! : ∀a. Ref a → a` reads a cell
+/// - `:= : ∀a. Ref a → a → Nothing` overwrites a cell
+/// - `true`, `false : Bool`
+/// - `map : ∀a b. (a → b) → List a → List b`
+/// - `filter : ∀a. (a → Bool) → List a → List a`
+/// - `fold : ∀a b. (b → a → b) → b → List a → b`, from the left
+/// - `length : ∀a. List a → Int`
+///
+/// They're only parsed the first time they're asked for.
+/// # Panics
+/// Never, since the built-in declarations parse
+#[must_use]
+pub fn default_environment() -> Environment {
+    static PRELUDE: OnceLock<Environment> = OnceLock::new();
+    PRELUDE
+        .get_or_init(|| {
+            parse_environment(include_str!("prelude.hhsi")).expect("the built-in prelude parses")
+        })
+        .clone()
+}
+
+/// read an environment's declarations, like those written by
+/// `environment_to_string`. Names declared more than once get the last
+/// scheme
+/// # Errors
+/// Returns a `ParseError` for text that isn't a list of declarations, or a
+/// declaration that mentions a type variable it doesn't quantify
+pub fn parse_environment(source: &str) -> Result<Environment, ParseError> {
+    let mut unknowns = HashMap::new();
+    parse_declarations(source)?
+        .into_iter()
+        .map(|declaration| {
+            let quantified = (declaration.quantified.iter())
+                .map(|name| (*name, fresh_type_id()))
+                .collect::<HashMap<_, _>>();
+            let ty =
+                to_type(&declaration.ty, &quantified, &mut unknowns).map_err(|var| ParseError {
+                    span: declaration.span,
+                    message: format!("type variable `{var}` isn't quantified"),
+                })?;
+            Ok((declaration.name, (quantified.into_values().collect(), ty)))
+        })
+        .collect()
+}
+
+/// declarations of every name in `environment`, sorted by name. Quantified
+/// type variables are named by order of appearance, so the same schemes are
+/// always written the same way, whatever ids inference gave them. Ones that
+/// aren't quantified are written `_0`, `_1` and so on
+#[must_use]
+pub fn environment_to_string(environment: &Environment) -> String {
+    let mut names = environment.keys().collect::<Vec<_>>();
+    names.sort_unstable();
+    let mut unknowns = HashMap::new();
+    let mut out = String::new();
+    for name in names {
+        let (quantified, ty) = &environment[name];
+        let mut ty = expand_aliases(ty);
+        let mut vars = Vec::new();
+        ty.vars_in_order(&mut vars);
+        let subs = (vars.into_iter())
+            .filter(|id| !quantified.contains(id))
+            .map(|id| {
+                let next = unknowns.len();
+                let var = *unknowns.entry(id).or_insert(next);
+                (id, Type::Con(intern(&format!("_{var}"))))
+            })
+            .collect();
+        ty.apply_subst(&subs);
+        let scheme: Scheme = (quantified.clone(), ty);
+        // writing to a String can't fail
+        let _ = writeln!(out, "{name} : {}", scheme_to_string(&scheme));
+    }
+    out
+}
+
+/// the type a declaration refers to, or the name of a type variable it
+/// doesn't quantify
+fn to_type(
+    ty: &TypeExpr,
+    quantified: &HashMap<Name, u32>,
+    unknowns: &mut HashMap<Name, u32>,
+) -> Result<Type, Name> {
+    Ok(match ty {
+        TypeExpr::Named("Int") => Type::Int,
+        TypeExpr::Named("Str") => Type::Str,
+        TypeExpr::Named("Nothing") => Type::Nothing,
+        TypeExpr::Named(name) => match quantified.get(name) {
+            Some(id) => Type::Unknown(*id),
+            None if name.starts_with('_') => {
+                Type::Unknown(*unknowns.entry(name).or_insert_with(fresh_type_id))
+            }
+            None if name.starts_with(char::is_lowercase) => return Err(name),
+            None => Type::Con(name),
+        },
+        TypeExpr::F(param, result) => Type::F(
+            to_type(param, quantified, unknowns)?.into(),
+            to_type(result, quantified, unknowns)?.into(),
+        ),
+        TypeExpr::App(constructor, arg) => Type::App(
+            to_type(constructor, quantified, unknowns)?.into(),
+            to_type(arg, quantified, unknowns)?.into(),
+        ),
+    })
+}
+
+/// copy of `ty` with aliases replaced by what they stand for, since
+/// declarations have nowhere to declare them
+pub(crate) fn expand_aliases(ty: &Type) -> Type {
+    use Type::*;
+    match ty {
+        Int | Str | Nothing | Unknown(_) | Con(_) => ty.clone(),
+        F(t1, t2) => F(expand_aliases(t1).into(), expand_aliases(t2).into()),
+        App(t1, t2) => App(expand_aliases(t1).into(), expand_aliases(t2).into()),
+        Alias(_, expansion) => expand_aliases(expansion),
+    }
+}
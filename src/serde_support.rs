@@ -0,0 +1,131 @@
+//! Helpers for the `serde` feature.
+//!
+//! Names are `&'static str`s, which can't borrow from a deserializer's
+//! input, so they're read as `String`s and interned. Schemes are written as
+//! `{"forall": [...], "type": ...}` with the quantified variables sorted, so
+//! the same scheme always serializes the same way.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{intern, Environment, Name, Scheme, Type};
+
+#[derive(Serialize, Deserialize)]
+struct SchemeRepr {
+    forall: Vec<u32>,
+    #[serde(rename = "type")]
+    ty: Type,
+}
+
+impl From<&Scheme> for SchemeRepr {
+    fn from((quantified, ty): &Scheme) -> Self {
+        let mut forall = quantified.iter().copied().collect::<Vec<_>>();
+        forall.sort_unstable();
+        SchemeRepr {
+            forall,
+            ty: ty.clone(),
+        }
+    }
+}
+
+impl From<SchemeRepr> for Scheme {
+    fn from(SchemeRepr { forall, ty }: SchemeRepr) -> Self {
+        (forall.into_iter().collect(), ty)
+    }
+}
+
+pub(crate) fn name<'de, D: Deserializer<'de>>(d: D) -> Result<&'static str, D::Error> {
+    Ok(intern(&String::deserialize(d)?))
+}
+
+pub(crate) fn optional_name<'de, D: Deserializer<'de>>(
+    d: D,
+) -> Result<Option<&'static str>, D::Error> {
+    Ok(Option::<String>::deserialize(d)?.map(|name| intern(&name)))
+}
+
+pub(crate) fn names<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<&'static str>, D::Error> {
+    Ok(Vec::<String>::deserialize(d)?
+        .iter()
+        .map(|name| intern(name))
+        .collect())
+}
+
+/// for lists of names paired with something else, like `let` bindings
+pub(crate) fn named<'de, D, T>(d: D) -> Result<Vec<(&'static str, T)>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Ok(Vec::<(String, T)>::deserialize(d)?
+        .into_iter()
+        .map(|(name, t)| (intern(&name), t))
+        .collect())
+}
+
+/// `#[serde(with = ...)]` for data constructors, `Vec<(&str, Scheme)>`
+pub(crate) mod constructors {
+    use super::{name, Deserialize, Deserializer, Name, Scheme, SchemeRepr, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        constructors: &[(&'static str, Scheme)],
+        s: S,
+    ) -> Result<S::Ok, S::Error> {
+        constructors
+            .iter()
+            .map(|(name, scheme)| (*name, SchemeRepr::from(scheme)))
+            .collect::<Vec<_>>()
+            .serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        d: D,
+    ) -> Result<Vec<(&'static str, Scheme)>, D::Error> {
+        #[derive(Deserialize)]
+        struct Constructor(#[serde(deserialize_with = "name")] Name, SchemeRepr);
+        Ok(Vec::<Constructor>::deserialize(d)?
+            .into_iter()
+            .map(|Constructor(name, scheme)| (name, scheme.into()))
+            .collect())
+    }
+}
+
+/// write a `Scheme`, which is a tuple and so can't implement `Serialize`
+/// itself
+/// # Errors
+/// Returns the serializer's error
+pub fn serialize_scheme<S: Serializer>(scheme: &Scheme, s: S) -> Result<S::Ok, S::Error> {
+    SchemeRepr::from(scheme).serialize(s)
+}
+
+/// read a `Scheme` written by `serialize_scheme`
+/// # Errors
+/// Returns the deserializer's error
+pub fn deserialize_scheme<'de, D: Deserializer<'de>>(d: D) -> Result<Scheme, D::Error> {
+    Ok(SchemeRepr::deserialize(d)?.into())
+}
+
+/// write an `Environment` as a map from name to scheme, ordered by name
+/// # Errors
+/// Returns the serializer's error
+pub fn serialize_environment<S: Serializer>(
+    environment: &Environment,
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    environment
+        .iter()
+        .map(|(name, scheme)| (*name, SchemeRepr::from(scheme)))
+        .collect::<BTreeMap<_, _>>()
+        .serialize(s)
+}
+
+/// read an `Environment` written by `serialize_environment`
+/// # Errors
+/// Returns the deserializer's error
+pub fn deserialize_environment<'de, D: Deserializer<'de>>(d: D) -> Result<Environment, D::Error> {
+    Ok(HashMap::<String, SchemeRepr>::deserialize(d)?
+        .into_iter()
+        .map(|(name, scheme)| (intern(&name), scheme.into()))
+        .collect())
+}
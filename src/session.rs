@@ -0,0 +1,201 @@
+use std::{
+    collections::HashSet,
+    fmt::{self, Write},
+};
+
+use itertools::Itertools;
+
+use crate::{
+    default_environment, generate_constraints, infer_type, parse, parse_expr, scheme_to_string,
+    ApplySubst, Environment, FreeVars, Name, ParseError, Scheme, Substitutions, Type, TypeError,
+    TypedIr,
+};
+
+/// Interactive inference state: the bindings made by earlier inputs stay in
+/// scope for later ones. Type declarations only last for their own input,
+/// but a type can't be declared again while bindings of it are in scope.
+#[derive(Debug, Clone)]
+pub struct Session {
+    environment: Environment,
+    /// what's in scope before any input
+    prelude: Environment,
+}
+
+/// Reasons an input to a `Session` can fail
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionError {
+    Parse(ParseError),
+    Type(TypeError),
+    /// a `:command` that doesn't exist
+    UnknownCommand(String),
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::with_environment(default_environment())
+    }
+}
+
+impl Session {
+    /// start a session with only the built-ins in scope
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// start a session with `prelude` in scope instead of the built-ins
+    #[must_use]
+    pub fn with_environment(prelude: Environment) -> Self {
+        Self {
+            environment: prelude.clone(),
+            prelude,
+        }
+    }
+    /// everything currently in scope
+    #[must_use]
+    pub fn environment(&self) -> &Environment {
+        &self.environment
+    }
+    /// forget every binding made so far
+    pub fn reset(&mut self) {
+        self.environment.clone_from(&self.prelude);
+    }
+    /// handle one input, returning the text to show for it. Inputs are
+    /// statements, or one of the commands
+    /// - `:type expr` shows the type of `expr`
+    /// - `:constraints expr` shows the constraints generated for `expr`
+    /// - `:env` shows every binding in scope
+    /// - `:reset` forgets every binding made so far
+    /// # Errors
+    /// Returns a `SessionError` if the input doesn't parse or typecheck, or
+    /// names an unknown command. The session is left unchanged
+    pub fn run(&mut self, input: &str) -> Result<String, SessionError> {
+        let input = input.trim();
+        let Some(command) = input.strip_prefix(':') else {
+            return self.statements(input);
+        };
+        let (command, arg) = command
+            .split_once(char::is_whitespace)
+            .unwrap_or((command, ""));
+        match command {
+            "type" | "t" => {
+                let ir = parse_expr(arg)?.desugar()?;
+                let (substitutions, typed) = infer_type(&self.environment, ir)?;
+                let scheme = generalize_top(&typed, &self.environment, &substitutions);
+                Ok(format!("{arg} : {}", scheme_to_string(&scheme)))
+            }
+            "constraints" | "c" => {
+                let ir = parse_expr(arg)?.desugar()?;
+                let (constraints, _) = generate_constraints(&self.environment, ir)?;
+                Ok(format!("{constraints}"))
+            }
+            "env" => Ok(self
+                .environment
+                .iter()
+                .sorted_by_key(|(name, _)| **name)
+                .map(|(name, scheme)| format!("{name} : {}", scheme_to_string(scheme)))
+                .join("\n")),
+            "reset" => {
+                self.reset();
+                Ok(String::new())
+            }
+            _ => Err(SessionError::UnknownCommand(command.to_owned())),
+        }
+    }
+
+    /// infer a line of statements, then bring its top-level bindings into
+    /// scope, showing them and the type of the expression it ends with. What the input fixed about the types already in scope, like
+    /// the element type of an empty `ref`, stays fixed for later inputs
+    fn statements(&mut self, input: &str) -> Result<String, SessionError> {
+        let ir = parse(input)?.desugar_program()?;
+        let (substitutions, typed) = infer_type(&self.environment, ir)?;
+        // values of a type an earlier input declared may still be in scope
+        let mut declared = Vec::new();
+        declared_data(&typed, &mut declared);
+        let redeclared = declared
+            .into_iter()
+            .find(|name| (self.environment.values()).any(|(_, ty)| ty.mentions(name)));
+        if let Some(name) = redeclared {
+            return Err(TypeError::DuplicateType(name).into());
+        }
+        for (_, ty) in self.environment.values_mut() {
+            ty.apply_subst(&substitutions);
+        }
+        let bindings = typed.top_level_bindings(&self.environment);
+        let mut out = String::new();
+        for (name, scheme) in bindings {
+            writeln!(out, "{name} : {}", scheme_to_string(&scheme)).unwrap();
+            self.environment.insert(name, scheme);
+        }
+        if let Some(expression) = typed.trailing_expression() {
+            let scheme = generalize_top(expression, &self.environment, &substitutions);
+            write!(out, "{}", scheme_to_string(&scheme)).unwrap();
+        }
+        Ok(out.trim_end().to_owned())
+    }
+}
+
+/// add the names of the data types declared anywhere in `typed` to `names`
+fn declared_data(typed: &TypedIr, names: &mut Vec<Name>) {
+    if let TypedIr::Data { constructors, .. } = typed {
+        for (_, (_, ty)) in constructors {
+            let mut ty = ty;
+            while let Type::F(_, result) = ty {
+                ty = result;
+            }
+            while let Type::App(constructor, _) = ty {
+                ty = constructor;
+            }
+            if let Type::Con(name) = ty {
+                names.push(name);
+            }
+        }
+    }
+    for child in typed.children() {
+        declared_data(child, names);
+    }
+}
+
+/// scheme of an input's expression, solved with `substitutions`. Only type
+/// variables from the bindings in scope stay monomorphic
+fn generalize_top(
+    typed: &TypedIr,
+    environment: &Environment,
+    substitutions: &Substitutions,
+) -> Scheme {
+    let ty = typed.ty().clone();
+    if !typed.is_value() {
+        return (HashSet::new(), ty);
+    }
+    let monomorphic = (environment.free_vars().into_iter())
+        .flat_map(|var| {
+            let mut ty = Type::Unknown(var);
+            ty.apply_subst(substitutions);
+            ty.free_vars()
+        })
+        .collect();
+    (&ty.free_vars() - &monomorphic, ty)
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionError::Parse(e) => write!(f, "{e}"),
+            SessionError::Type(e) => write!(f, "{e}"),
+            SessionError::UnknownCommand(command) => write!(f, "unknown command `:{command}`"),
+        }
+    }
+}
+
+impl std::error::Error for SessionError {}
+
+impl From<ParseError> for SessionError {
+    fn from(e: ParseError) -> Self {
+        SessionError::Parse(e)
+    }
+}
+
+impl From<TypeError> for SessionError {
+    fn from(e: TypeError) -> Self {
+        SessionError::Type(e)
+    }
+}
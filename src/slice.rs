@@ -0,0 +1,138 @@
+//! Type error slicing, after Haack and Wells' "Type error slicing in
+//! implicitly typed higher-order languages".
+//!
+//! Blaming a type error on a single expression is often misleading, since
+//! the error is really a disagreement between several of them. A slice is
+//! every part of the program that takes part in that disagreement, and
+//! nothing else: the expressions that the constraints of a minimal
+//! unsatisfiable subset were generated for. An expression's subexpressions
+//! aren't part of the slice unless they take part too, so only the text
+//! around them is.
+
+use std::collections::HashSet;
+
+use crate::{
+    fresh_type_var, generate_constraints, minimal_unsatisfiable_subset, Environment, Ir, Span,
+    TypedIr,
+};
+
+/// the pieces of source text of `program` that contribute to its type
+/// error in `environment`, in order. `None` if it has no type error, or
+/// one that isn't a disagreement between its constraints, like an unbound
+/// identifier.
+///
+/// A piece can be only whitespace, for an expression like an application
+/// that has no text of its own besides its subexpressions
+#[must_use]
+pub fn type_error_slice(environment: &Environment, program: Ir) -> Option<Vec<Span>> {
+    let (constraints, typed) = generate_constraints(environment, named(program)).ok()?;
+    let blamed = minimal_unsatisfiable_subset(&constraints)?
+        .into_iter()
+        .filter_map(|(_, origin)| origin)
+        .collect::<HashSet<_>>();
+    let mut slice = Vec::new();
+    collect(&typed, &blamed, &mut slice);
+    slice.sort_by_key(|span| (span.start, span.end));
+    slice.dedup();
+    Some(slice)
+}
+
+/// `program` with each located node that makes its own type, like a
+/// literal, annotated with a fresh type variable. Otherwise the type is
+/// written straight into its parent's constraints, which can then only be
+/// blamed on the parent: `1 + "s"` would blame `+` but not `"s"`
+fn named(program: Ir) -> Ir {
+    use Ir::*;
+    let named_box = |ir: Box<Ir>| Box::new(named(*ir));
+    match program {
+        Located(span, inner) => {
+            let makes_its_type = matches!(
+                *inner,
+                LiteralInt(_) | LiteralStr(_) | Lam { .. } | List(_) | Annot { .. }
+            );
+            let inner = named(*inner);
+            let inner = if makes_its_type {
+                Annot {
+                    expr: Box::new(inner),
+                    ty: fresh_type_var(),
+                }
+            } else {
+                inner
+            };
+            Located(span, Box::new(inner))
+        }
+        App { e1, e2 } => App {
+            e1: named_box(e1),
+            e2: named_box(e2),
+        },
+        Lam { binding, body } => Lam {
+            binding,
+            body: named_box(body),
+        },
+        Let { e1, binding, e2 } => Let {
+            e1: named_box(e1),
+            binding,
+            e2: named_box(e2),
+        },
+        Add(e1, e2) => Add(named_box(e1), named_box(e2)),
+        Seq(e1, e2) => Seq(named_box(e1), named_box(e2)),
+        Annot { expr, ty } => Annot {
+            expr: named_box(expr),
+            ty,
+        },
+        Data { constructors, e2 } => Data {
+            constructors,
+            e2: named_box(e2),
+        },
+        List(items) => List(items.into_iter().map(named).collect()),
+        Match { scrutinee, arms } => Match {
+            scrutinee: named_box(scrutinee),
+            arms: (arms.into_iter())
+                .map(|(pattern, body)| (pattern, named(body)))
+                .collect(),
+        },
+        Nop | LiteralStr(_) | LiteralInt(_) | Id(_) | Hole(_) => program,
+    }
+}
+
+/// add the text of each blamed expression in `node`, without its
+/// subexpressions, to `slice`
+fn collect(node: &TypedIr, blamed: &HashSet<Span>, slice: &mut Vec<Span>) {
+    if let TypedIr::Located(span, inner) = node {
+        if blamed.contains(span) {
+            let mut subexpressions = Vec::new();
+            located_within(inner, *span, &mut subexpressions);
+            subexpressions.sort_by_key(|span| span.start);
+            let mut start = span.start;
+            for subexpression in subexpressions {
+                if start < subexpression.start {
+                    slice.push(Span::new(start, subexpression.start));
+                }
+                start = start.max(subexpression.end);
+            }
+            if start < span.end {
+                slice.push(Span::new(start, span.end));
+            }
+        }
+    }
+    for child in node.children() {
+        collect(child, blamed, slice);
+    }
+}
+
+/// the spans of the outermost located nodes in `node` that lie within
+/// `span`. A statement's node also holds the statements after it, which
+/// don't
+fn located_within(node: &TypedIr, span: Span, spans: &mut Vec<Span>) {
+    match node {
+        TypedIr::Located(inner, _) if span.start <= inner.start && inner.end <= span.end => {
+            spans.push(*inner);
+        }
+        TypedIr::Located(..) => (),
+        _ => {
+            for child in node.children() {
+                located_within(child, span, spans);
+            }
+        }
+    }
+}
@@ -1,30 +1,79 @@
-use crate::{compose, generalize, instantiate, mgu, ApplySubst, Constraints, Substitutions};
+use std::collections::HashSet;
+
+use crate::{
+    compose, generalize, instantiate, mgu, ApplySubst, Constraints, ExplicitInstance, SolveEvent,
+    Substitutions, TypeError,
+};
 
 /// attempt to solve a set of constraints
-#[must_use]
-pub fn solve(cs: Constraints) -> Substitutions {
+/// # Errors
+/// Returns the `TypeError` from the first equality constraint that cannot be unified
+pub fn solve(cs: Constraints) -> Result<Substitutions, TypeError> {
+    solve_traced(cs, &mut |_| ())
+}
+
+/// `solve`, reporting each step it takes to `on_event`
+/// # Errors
+/// Returns the `TypeError` from the first equality constraint that cannot be
+/// unified, or `TypeError::CircularGeneralization` if no implicit instance
+/// constraint can be solved before the others. That never happens for
+/// constraints made by `generate_constraints`, but can for a subset of them
+pub fn solve_traced(
+    cs: Constraints,
+    on_event: &mut dyn FnMut(SolveEvent),
+) -> Result<Substitutions, TypeError> {
     let mut cs = cs;
     let mut subs = Substitutions::new();
     while !cs.is_empty() {
         if let Some(c) = cs.next_eq() {
             cs.remove_eq(&c);
-            let (t1, t2) = c;
-            let s = mgu(&t1, &t2);
+            let (t1, t2) = &c;
+            let s = match mgu(t1, t2) {
+                Ok(s) => s,
+                Err(error) => {
+                    on_event(SolveEvent::Fail {
+                        constraint: c,
+                        error: error.clone(),
+                    });
+                    return Err(error);
+                }
+            };
             cs.apply_subst(&s);
-            subs = compose(s, subs);
+            subs = compose(s.clone(), subs);
+            on_event(SolveEvent::Unify {
+                constraint: c,
+                mgu: s,
+                substitution: subs.clone(),
+            });
         } else if let Some(exp) = cs.next_explicit() {
             cs.remove_exp(&exp);
             let t2 = instantiate(exp.scheme.0.iter().copied(), &exp.scheme.1);
-            cs.insert_eq(exp.instance, t2);
+            cs.insert_eq(exp.instance.clone(), t2.clone());
+            on_event(SolveEvent::Instantiate {
+                result: (exp.instance.clone(), t2),
+                constraint: exp,
+            });
             // TODO: could probably save a step and instead of adding a constraint, turn the explicit instance constraint into a substitution directly
         } else if let Some(imp) = cs.next_implicit() {
             cs.remove_imp(&imp);
             // TODO: it seems to me we could save a couple steps by reinstantiating and unifying right away
-            let scheme = generalize(&imp.monomorphics, &imp.to_generalize);
-            cs.insert_explicit(imp.instance, scheme);
+            let scheme = if imp.generalizable {
+                generalize(&imp.monomorphics, &imp.to_generalize)
+            } else {
+                (HashSet::new(), imp.to_generalize.clone())
+            };
+            cs.insert_explicit(imp.instance.clone(), scheme.clone());
+            on_event(SolveEvent::Generalize {
+                result: ExplicitInstance {
+                    instance: imp.instance.clone(),
+                    scheme,
+                },
+                constraint: imp,
+            });
         } else {
-            unreachable!("unhandled case in solve! {cs:?}");
+            // only implicit instances are left, each waiting on another
+            return Err(TypeError::CircularGeneralization);
         }
     }
-    subs
+    Ok(subs)
 }
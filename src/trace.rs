@@ -0,0 +1,126 @@
+use std::fmt;
+
+use itertools::Itertools;
+
+use crate::{json::Json, ExplicitInstance, ImplicitInstance, Substitutions, Type, TypeError};
+
+/// One step taken by `solve_traced`
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum SolveEvent {
+    /// an equality constraint was unified, extending the substitution
+    Unify {
+        constraint: (Type, Type),
+        mgu: Substitutions,
+        /// everything learned so far, including `mgu`
+        substitution: Substitutions,
+    },
+    /// an explicit instance constraint became an equality with a fresh
+    /// instance of its scheme
+    Instantiate {
+        constraint: ExplicitInstance,
+        result: (Type, Type),
+    },
+    /// an implicit instance constraint became an explicit one, now that the
+    /// type it generalizes is known
+    Generalize {
+        constraint: ImplicitInstance,
+        result: ExplicitInstance,
+    },
+    /// an equality constraint could not be unified, ending the solve
+    Fail {
+        constraint: (Type, Type),
+        error: TypeError,
+    },
+}
+
+impl SolveEvent {
+    /// the event as a single-line JSON object, with types pretty-printed
+    /// as strings
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        let equality = |t1: &Type, t2: &Type| Json::from(format!("{t1} ≡ {t2}"));
+        let json = match self {
+            SolveEvent::Unify {
+                constraint: (t1, t2),
+                mgu,
+                substitution,
+            } => Json::object([
+                ("event", "unify".into()),
+                ("constraint", equality(t1, t2)),
+                ("mgu", substitution_json(mgu)),
+                ("substitution", substitution_json(substitution)),
+            ]),
+            SolveEvent::Instantiate {
+                constraint,
+                result: (t1, t2),
+            } => Json::object([
+                ("event", "instantiate".into()),
+                ("constraint", constraint.to_string().into()),
+                ("result", equality(t1, t2)),
+            ]),
+            SolveEvent::Generalize { constraint, result } => Json::object([
+                ("event", "generalize".into()),
+                ("constraint", constraint.to_string().into()),
+                ("result", result.to_string().into()),
+            ]),
+            SolveEvent::Fail {
+                constraint: (t1, t2),
+                error,
+            } => Json::object([
+                ("event", "fail".into()),
+                ("constraint", equality(t1, t2)),
+                ("error", error.to_string().into()),
+            ]),
+        };
+        json.to_string()
+    }
+}
+
+impl fmt::Display for SolveEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SolveEvent::Unify {
+                constraint: (t1, t2),
+                mgu,
+                substitution,
+            } => write!(
+                f,
+                "unify {t1} ≡ {t2}\n  mgu: {}\n  substitution: {}",
+                SubstitutionDisplay(mgu),
+                SubstitutionDisplay(substitution)
+            ),
+            SolveEvent::Instantiate {
+                constraint,
+                result: (t1, t2),
+            } => write!(f, "instantiate {constraint}\n  gives {t1} ≡ {t2}"),
+            SolveEvent::Generalize { constraint, result } => {
+                write!(f, "generalize {constraint}\n  gives {result}")
+            }
+            SolveEvent::Fail {
+                constraint: (t1, t2),
+                error,
+            } => write!(f, "fail {t1} ≡ {t2}\n  {error}"),
+        }
+    }
+}
+
+/// `[t1 := Int, t2 := t1 -> t1]`, ordered by variable
+struct SubstitutionDisplay<'a>(&'a Substitutions);
+
+impl fmt::Display for SubstitutionDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bindings = (self.0.iter())
+            .sorted_by_key(|(id, _)| **id)
+            .format_with(", ", |(id, ty), f| f(&format_args!("t{id} := {ty}")));
+        write!(f, "[{bindings}]")
+    }
+}
+
+/// `{"t1": "Int", "t2": "t1 -> t1"}`, ordered by variable
+fn substitution_json(subs: &Substitutions) -> Json {
+    let bindings = (subs.iter())
+        .sorted_by_key(|(id, _)| **id)
+        .map(|(id, ty)| (format!("t{id}"), ty.to_string().into()))
+        .collect();
+    Json::Object(bindings)
+}
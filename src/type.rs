@@ -1,10 +1,11 @@
-use std::collections::HashSet;
+use std::{collections::HashSet, fmt};
 
-use crate::FreeVars;
+use crate::{intern, ApplySubst, FreeVars, Name, Substitutions};
 
 pub type Scheme = (HashSet<u32>, Type);
 
 #[derive(Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Type {
     Int,
     Str,
@@ -12,15 +13,144 @@ pub enum Type {
     F(Box<Type>, Box<Type>),
     //Tuple(Box<Type>, Box<Type>),
     Unknown(u32),
+    /// user-declared name for another type. Unifies exactly like its
+    /// expansion, but keeps the name around for printing
+    Alias(
+        #[cfg_attr(
+            feature = "serde",
+            serde(deserialize_with = "crate::serde_support::name")
+        )]
+        Name,
+        Box<Type>,
+    ),
+    /// named type constructor, from a data declaration or built in
+    Con(
+        #[cfg_attr(
+            feature = "serde",
+            serde(deserialize_with = "crate::serde_support::name")
+        )]
+        Name,
+    ),
+    /// type constructor applied to an argument. The constructor may itself
+    /// be a type variable, as in `f a`
+    App(Box<Type>, Box<Type>),
+}
+
+impl Type {
+    /// `List a`, the built-in list type
+    #[must_use]
+    pub fn list(element: Type) -> Type {
+        Type::App(Type::Con("List").into(), element.into())
+    }
+    /// `Ref a`, the type of a mutable cell holding an `a`
+    #[must_use]
+    pub fn reference(inner: Type) -> Type {
+        Type::App(Type::Con("Ref").into(), inner.into())
+    }
+    /// strip any aliases off the outside of the type
+    #[must_use]
+    pub fn expand(&self) -> &Type {
+        match self {
+            Type::Alias(_, expansion) => expansion.expand(),
+            t => t,
+        }
+    }
+    /// whether the type constructor called `name` appears in the type
+    #[must_use]
+    pub fn mentions(&self, name: Name) -> bool {
+        use Type::*;
+        match self {
+            Int | Str | Nothing | Unknown(_) => false,
+            Con(con) => *con == name,
+            F(t1, t2) | App(t1, t2) => t1.mentions(name) || t2.mentions(name),
+            Alias(_, expansion) => expansion.mentions(name),
+        }
+    }
+    /// type variables in the order they're first mentioned
+    pub(crate) fn vars_in_order(&self, order: &mut Vec<u32>) {
+        use Type::*;
+        match self {
+            Int | Str | Nothing | Con(_) => (),
+            F(t1, t2) | App(t1, t2) => {
+                t1.vars_in_order(order);
+                t2.vars_in_order(order);
+            }
+            Unknown(id) => {
+                if !order.contains(id) {
+                    order.push(*id);
+                }
+            }
+            Alias(_, expansion) => expansion.vars_in_order(order),
+        }
+    }
+}
+
+/// print a scheme like `forall a b. (a -> b) -> List a -> List b`, naming the
+/// quantified variables by order of appearance
+#[must_use]
+pub fn scheme_to_string((quantified, ty): &Scheme) -> String {
+    let mut order = Vec::new();
+    ty.vars_in_order(&mut order);
+    order.retain(|id| quantified.contains(id));
+    if order.is_empty() {
+        return ty.to_string();
+    }
+    let names = (0..order.len()).map(var_name).collect::<Vec<_>>();
+    let subs: Substitutions = order
+        .iter()
+        .zip(&names)
+        .map(|(id, name)| (*id, Type::Con(intern(name))))
+        .collect();
+    let mut ty = ty.clone();
+    ty.apply_subst(&subs);
+    format!("forall {}. {ty}", names.join(" "))
+}
+
+/// `a`, `b`, ... `z`, `a1`, `b1`, ...
+fn var_name(i: usize) -> String {
+    let letter = char::from(b"abcdefghijklmnopqrstuvwxyz"[i % 26]);
+    if i < 26 {
+        letter.to_string()
+    } else {
+        format!("{letter}{}", i / 26)
+    }
 }
 
 impl FreeVars for Type {
     fn free_vars(&self) -> HashSet<u32> {
         use Type::*;
         match self {
-            Int | Str | Nothing => HashSet::new(),
-            F(t1, t2) => &t1.free_vars() | &t2.free_vars(),
+            Int | Str | Nothing | Con(_) => HashSet::new(),
+            F(t1, t2) | App(t1, t2) => &t1.free_vars() | &t2.free_vars(),
             Unknown(id) => [*id].into(),
+            Alias(_, expansion) => expansion.free_vars(),
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Type::*;
+        match self {
+            Int => write!(f, "Int"),
+            Str => write!(f, "Str"),
+            Nothing => write!(f, "Nothing"),
+            F(param, result) if matches!(**param, F(..)) => write!(f, "({param}) -> {result}"),
+            F(param, result) => write!(f, "{param} -> {result}"),
+            Unknown(id) => write!(f, "t{id}"),
+            Alias(name, _) | Con(name) => write!(f, "{name}"),
+            App(constructor, arg) => {
+                if matches!(**constructor, F(..)) {
+                    write!(f, "({constructor})")?;
+                } else {
+                    write!(f, "{constructor}")?;
+                }
+                if matches!(**arg, F(..) | App(..)) {
+                    write!(f, " ({arg})")
+                } else {
+                    write!(f, " {arg}")
+                }
+            }
         }
     }
 }
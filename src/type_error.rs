@@ -0,0 +1,63 @@
+use std::fmt;
+
+use itertools::Itertools;
+
+use crate::{Hole, Kind, Type};
+
+/// Reasons a program can fail to typecheck
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum TypeError {
+    /// two types were required to be equal but could not be unified
+    Mismatch(Type, Type),
+    /// identifiers that are bound neither in the expression nor in the environment
+    UnboundIdentifiers(Vec<&'static str>),
+    /// a type variable would have to equal a type that contains it
+    InfiniteType(Type, Type),
+    /// a type annotation referred to a type name that was never declared
+    UnknownType(&'static str),
+    /// a pattern binds the same name more than once, as in `x :: x`, or a
+    /// function or data declaration repeats a parameter or constructor
+    DuplicateBinding(&'static str),
+    /// a data declaration reuses the name of a data type that's still in
+    /// scope, whose values would then be taken for the new type's
+    DuplicateType(&'static str),
+    /// an `import` or `export` statement inside a block, rather than among
+    /// the top-level statements of a module
+    NotAtTopLevel(&'static str),
+    /// a type was used with the wrong number or kind of arguments
+    KindMismatch(Kind, Kind),
+    /// every remaining implicit instance constraint would generalize a type
+    /// variable another one still mentions, so none can be solved first
+    CircularGeneralization,
+    /// the program has holes, which inference can describe but not fill
+    Holes(Vec<Hole>),
+}
+
+impl fmt::Display for TypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use TypeError::*;
+        match self {
+            Mismatch(t1, t2) => write!(f, "unable to unify types: {t1} and {t2}"),
+            UnboundIdentifiers(ids) => {
+                write!(f, "unrecognized identifiers: {}", ids.iter().join(", "))
+            }
+            InfiniteType(var, ty) => write!(f, "infinite type: {var} would be {ty}"),
+            UnknownType(name) => write!(f, "unknown type `{name}`"),
+            DuplicateBinding(name) => write!(f, "`{name}` is bound more than once"),
+            DuplicateType(name) => write!(f, "type `{name}` is already declared"),
+            NotAtTopLevel(keyword) => {
+                write!(
+                    f,
+                    "`{keyword}` is only allowed at the top level of a module"
+                )
+            }
+            KindMismatch(k1, k2) => write!(f, "unable to unify kinds: {k1} and {k2}"),
+            CircularGeneralization => {
+                write!(f, "no `let` can be generalized before the others")
+            }
+            Holes(holes) => write!(f, "{}", holes.iter().join("\n")),
+        }
+    }
+}
+
+impl std::error::Error for TypeError {}
@@ -0,0 +1,129 @@
+use std::{collections::HashMap, fmt};
+
+use crate::{Kind, Name, Type, TypeError};
+
+/// A type as written in a program, before names have been resolved.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TypeExpr {
+    /// reference to a built-in type, a declared alias or data type, or a
+    /// data type parameter, like `Int`, `Handler` or `a`
+    Named(
+        #[cfg_attr(
+            feature = "serde",
+            serde(deserialize_with = "crate::serde_support::name")
+        )]
+        Name,
+    ),
+    F(Box<TypeExpr>, Box<TypeExpr>),
+    /// type constructor applied to an argument, like `List Int` or `f a`
+    App(Box<TypeExpr>, Box<TypeExpr>),
+}
+
+impl fmt::Display for TypeExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use TypeExpr::*;
+        match self {
+            Named(name) => write!(f, "{name}"),
+            F(param, result) if matches!(**param, F(..)) => write!(f, "({param}) -> {result}"),
+            F(param, result) => write!(f, "{param} -> {result}"),
+            App(constructor, arg) => {
+                if matches!(**constructor, F(..)) {
+                    write!(f, "({constructor})")?;
+                } else {
+                    write!(f, "{constructor}")?;
+                }
+                if matches!(**arg, F(..) | App(..)) {
+                    write!(f, " ({arg})")
+                } else {
+                    write!(f, " {arg}")
+                }
+            }
+        }
+    }
+}
+
+/// Type names that are visible at some point in a program, along with their kinds
+#[derive(Debug, Clone)]
+pub struct TypeScope {
+    names: HashMap<&'static str, (Type, Kind)>,
+}
+
+impl Default for TypeScope {
+    fn default() -> Self {
+        Self {
+            names: [
+                ("Int", (Type::Int, Kind::Star)),
+                ("Str", (Type::Str, Kind::Star)),
+                ("Nothing", (Type::Nothing, Kind::Star)),
+                ("Bool", (Type::Con("Bool"), Kind::Star)),
+                (
+                    "List",
+                    (
+                        Type::Con("List"),
+                        Kind::Arrow(Kind::Star.into(), Kind::Star.into()),
+                    ),
+                ),
+                (
+                    "Ref",
+                    (
+                        Type::Con("Ref"),
+                        Kind::Arrow(Kind::Star.into(), Kind::Star.into()),
+                    ),
+                ),
+            ]
+            .into(),
+        }
+    }
+}
+
+impl TypeScope {
+    /// create a scope containing only the built-in types
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// make `name` refer to `expansion` from now on
+    pub fn declare_alias(&mut self, name: &'static str, expansion: Type, kind: Kind) {
+        self.names
+            .insert(name, (Type::Alias(name, expansion.into()), kind));
+    }
+    /// make `name` refer to the type constructor of a data declaration
+    pub fn declare_data(&mut self, name: &'static str, kind: Kind) {
+        self.names.insert(name, (Type::Con(name), kind));
+    }
+    /// whether `name` refers to a data type, declared or built in
+    #[must_use]
+    pub fn declares_data(&self, name: &str) -> bool {
+        matches!(self.names.get(name), Some((Type::Con(_), _)))
+    }
+    /// make `name` refer to a type variable, such as a data type parameter
+    pub fn declare_var(&mut self, name: &'static str, id: u32, kind: Kind) {
+        self.names.insert(name, (Type::Unknown(id), kind));
+    }
+    /// kind of the type that `name` refers to, if it's in scope
+    #[must_use]
+    pub fn kind(&self, name: &str) -> Option<&Kind> {
+        self.names.get(name).map(|(_, kind)| kind)
+    }
+    /// turn a `TypeExpr` into the `Type` it refers to in this scope
+    /// # Errors
+    /// Returns `TypeError::UnknownType` if a name isn't in scope
+    pub fn resolve(&self, ty: &TypeExpr) -> Result<Type, TypeError> {
+        match ty {
+            TypeExpr::Named(name) => self
+                .names
+                .get(name)
+                .map(|(ty, _)| ty.clone())
+                .ok_or(TypeError::UnknownType(name)),
+            TypeExpr::F(param, result) => Ok(Type::F(
+                self.resolve(param)?.into(),
+                self.resolve(result)?.into(),
+            )),
+            TypeExpr::App(constructor, arg) => Ok(Type::App(
+                self.resolve(constructor)?.into(),
+                self.resolve(arg)?.into(),
+            )),
+        }
+    }
+}
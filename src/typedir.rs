@@ -1,31 +1,81 @@
 #![allow(clippy::missing_errors_doc)]
-use crate::{ApplySubst, Substitutions, Type};
-use std::fmt::Write;
+use crate::{
+    hole::hole_text, ApplySubst, Environment, FreeVars, Ir, Name, Pattern, Scheme, Span,
+    Substitutions, Type,
+};
+use std::{collections::HashSet, fmt::Write};
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TypedIr {
     Nop(Type),
     LiteralInt(i64, Type),
-    LiteralStr(&'static str, Type),
-    Id(&'static str, Type),
+    LiteralStr(
+        #[cfg_attr(
+            feature = "serde",
+            serde(deserialize_with = "crate::serde_support::name")
+        )]
+        Name,
+        Type,
+    ),
+    Id(
+        #[cfg_attr(
+            feature = "serde",
+            serde(deserialize_with = "crate::serde_support::name")
+        )]
+        Name,
+        Type,
+    ),
     App {
         e1: Box<TypedIr>,
         e2: Box<TypedIr>,
         ty: Type,
     },
     Lam {
-        binding: &'static str,
+        #[cfg_attr(
+            feature = "serde",
+            serde(deserialize_with = "crate::serde_support::name")
+        )]
+        binding: Name,
         body: Box<TypedIr>,
         ty: Type,
     },
     Let {
         e1: Box<TypedIr>,
-        binding: &'static str,
+        #[cfg_attr(
+            feature = "serde",
+            serde(deserialize_with = "crate::serde_support::name")
+        )]
+        binding: Name,
         e2: Box<TypedIr>,
         ty: Type,
     },
     Add(Box<TypedIr>, Box<TypedIr>),
     Seq(Box<TypedIr>, Box<TypedIr>),
+    Annot {
+        expr: Box<TypedIr>,
+        ty: Type,
+    },
+    Data {
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_support::constructors"))]
+        constructors: Vec<(Name, Scheme)>,
+        e2: Box<TypedIr>,
+    },
+    List(Vec<TypedIr>, Type),
+    Hole(
+        #[cfg_attr(
+            feature = "serde",
+            serde(deserialize_with = "crate::serde_support::optional_name")
+        )]
+        Option<Name>,
+        Type,
+    ),
+    Match {
+        scrutinee: Box<TypedIr>,
+        arms: Vec<(Pattern, TypedIr)>,
+        ty: Type,
+    },
+    Located(Span, Box<TypedIr>),
 }
 impl TypedIr {
     #[must_use]
@@ -38,8 +88,186 @@ impl TypedIr {
             | Id(_, ty)
             | App { ty, .. }
             | Lam { ty, .. }
-            | Let { ty, .. } => ty,
-            Add(inner, _) | Seq(_, inner) => inner.ty(),
+            | Let { ty, .. }
+            | Annot { ty, .. }
+            | List(_, ty)
+            | Hole(_, ty)
+            | Match { ty, .. } => ty,
+            Add(inner, _) | Seq(_, inner) | Data { e2: inner, .. } | Located(_, inner) => {
+                inner.ty()
+            }
+        }
+    }
+
+    /// Whether this is a syntactic value for the purposes of the value
+    /// restriction: evaluating it can't allocate a `Ref`, so its type is
+    /// safe to generalize
+    #[must_use]
+    pub fn is_value(&self) -> bool {
+        use TypedIr::*;
+        match self {
+            Nop(_) | LiteralInt(..) | LiteralStr(..) | Id(..) | Hole(..) | Lam { .. } => true,
+            App { .. } | Add(..) | Seq(..) | Match { .. } => false,
+            List(elements, _) => elements.iter().all(TypedIr::is_value),
+            Let { e1, e2, .. } => e1.is_value() && e2.is_value(),
+            Annot { expr, .. } | Located(_, expr) => expr.is_value(),
+            Data { e2, .. } => e2.is_value(),
+        }
+    }
+
+    /// location of this node in the source text, if known
+    #[must_use]
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            TypedIr::Located(span, _) => Some(*span),
+            _ => None,
+        }
+    }
+
+    /// the immediate subexpressions of this node
+    #[must_use]
+    pub fn children(&self) -> Vec<&TypedIr> {
+        use TypedIr::*;
+        match self {
+            Nop(_) | LiteralInt(..) | LiteralStr(..) | Id(..) | Hole(..) => Vec::new(),
+            App { e1, e2, .. } | Let { e1, e2, .. } | Add(e1, e2) | Seq(e1, e2) => {
+                vec![e1, e2]
+            }
+            Lam { body, .. } | Annot { expr: body, .. } | Data { e2: body, .. } => vec![body],
+            List(elements, _) => elements.iter().collect(),
+            Match {
+                scrutinee, arms, ..
+            } => std::iter::once(&**scrutinee)
+                .chain(arms.iter().map(|(_, arm)| arm))
+                .collect(),
+            Located(_, inner) => vec![inner],
+        }
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut TypedIr> {
+        use TypedIr::*;
+        match self {
+            Nop(_) | LiteralInt(..) | LiteralStr(..) | Id(..) | Hole(..) => Vec::new(),
+            App { e1, e2, .. } | Let { e1, e2, .. } | Add(e1, e2) | Seq(e1, e2) => {
+                vec![e1, e2]
+            }
+            Lam { body, .. } | Annot { expr: body, .. } | Data { e2: body, .. } => vec![body],
+            List(elements, _) => elements.iter_mut().collect(),
+            Match {
+                scrutinee, arms, ..
+            } => std::iter::once(&mut **scrutinee)
+                .chain(arms.iter_mut().map(|(_, arm)| arm))
+                .collect(),
+            Located(_, inner) => vec![inner],
+        }
+    }
+
+    /// move every location in the tree, as after an edit to the source text
+    pub(crate) fn relocate(&mut self, f: &impl Fn(Span) -> Span) {
+        if let TypedIr::Located(span, _) = self {
+            *span = f(*span);
+        }
+        for child in self.children_mut() {
+            child.relocate(f);
+        }
+    }
+
+    /// schemes of the names bound by a program's top-level statements,
+    /// including data constructors, in order. Every type variable is
+    /// generalized unless the value restriction forbids it, or it's free in
+    /// `environment` or in the type of an earlier binding that wasn't
+    /// generalized, since those are still monomorphic
+    #[must_use]
+    pub fn top_level_bindings(&self, environment: &Environment) -> Vec<(&'static str, Scheme)> {
+        use TypedIr::*;
+        let mut bindings = Vec::new();
+        let mut monomorphic = environment.free_vars();
+        let mut node = self;
+        loop {
+            match node {
+                Located(_, inner) | Seq(_, inner) => node = inner,
+                Data { constructors, e2 } => {
+                    bindings.extend(constructors.iter().cloned());
+                    node = e2;
+                }
+                Let {
+                    e1, binding, e2, ..
+                } => {
+                    let ty = e1.ty().clone();
+                    let quantified = if e1.is_value() {
+                        &ty.free_vars() - &monomorphic
+                    } else {
+                        monomorphic.extend(ty.free_vars());
+                        HashSet::new()
+                    };
+                    bindings.push((*binding, (quantified, ty)));
+                    node = e2;
+                }
+                _ => return bindings,
+            }
+        }
+    }
+
+    /// the expression statement a program ends with, if it ends with one
+    #[must_use]
+    pub fn trailing_expression(&self) -> Option<&TypedIr> {
+        use TypedIr::*;
+        match self {
+            Located(_, inner) | Data { e2: inner, .. } | Let { e2: inner, .. } => {
+                inner.trailing_expression()
+            }
+            Seq(e, rest) if matches!(**rest, Nop(_)) => Some(e),
+            Seq(_, rest) => rest.trailing_expression(),
+            _ => None,
+        }
+    }
+
+    /// the untyped `Ir` this was inferred from, e.g. for evaluation
+    #[must_use]
+    pub fn erase(&self) -> Ir {
+        use TypedIr::*;
+        match self {
+            Nop(_) => Ir::Nop,
+            LiteralInt(i, _) => Ir::LiteralInt(*i),
+            LiteralStr(s, _) => Ir::LiteralStr(s),
+            Id(s, _) => Ir::Id(s),
+            Hole(name, _) => Ir::Hole(*name),
+            App { e1, e2, .. } => Ir::App {
+                e1: e1.erase().into(),
+                e2: e2.erase().into(),
+            },
+            Lam { binding, body, .. } => Ir::Lam {
+                binding,
+                body: body.erase().into(),
+            },
+            Let {
+                e1, binding, e2, ..
+            } => Ir::Let {
+                e1: e1.erase().into(),
+                binding,
+                e2: e2.erase().into(),
+            },
+            Add(lhs, rhs) => Ir::Add(lhs.erase().into(), rhs.erase().into()),
+            Seq(lhs, rhs) => Ir::Seq(lhs.erase().into(), rhs.erase().into()),
+            Annot { expr, ty } => Ir::Annot {
+                expr: expr.erase().into(),
+                ty: ty.clone(),
+            },
+            Data { constructors, e2 } => Ir::Data {
+                constructors: constructors.clone(),
+                e2: e2.erase().into(),
+            },
+            List(elements, _) => Ir::List(elements.iter().map(TypedIr::erase).collect()),
+            Match {
+                scrutinee, arms, ..
+            } => Ir::Match {
+                scrutinee: scrutinee.erase().into(),
+                arms: arms
+                    .iter()
+                    .map(|(pattern, arm)| (pattern.clone(), arm.erase()))
+                    .collect(),
+            },
+            Located(span, inner) => Ir::Located(*span, inner.erase().into()),
         }
     }
 
@@ -49,19 +277,21 @@ impl TypedIr {
     fn display_tree_(&self, prefix: &str) {
         use TypedIr::*;
         match self {
-            Nop(ty) => println!("{prefix}+-NOP {ty:?}"),
-            LiteralStr(s, ty) => println!("{prefix}+-\"{s}\" : {ty:?}"),
-            LiteralInt(i, ty) => println!("{prefix}+-{i} : {ty:?}"),
-            Id(s, ty) => println!("{prefix}+-ID `{s}` : {ty:?}"),
+            Located(_, inner) => inner.display_tree_(prefix),
+            Nop(ty) => println!("{prefix}+-NOP {ty}"),
+            LiteralStr(s, ty) => println!("{prefix}+-\"{s}\" : {ty}"),
+            LiteralInt(i, ty) => println!("{prefix}+-{i} : {ty}"),
+            Id(s, ty) => println!("{prefix}+-ID `{s}` : {ty}"),
+            Hole(name, ty) => println!("{prefix}+-HOLE {} : {ty}", hole_text(*name)),
             App { e1, e2, ty } => {
-                println!("{prefix}+-App {ty:?}");
+                println!("{prefix}+-App {ty}");
                 println!("{prefix}  |  |");
                 e1.display_tree_(&format!("{prefix}  |  "));
                 println!("{prefix}  |");
                 e2.display_tree_(&format!("{prefix}  "));
             }
             Lam { binding, body, ty } => {
-                println!("{prefix}+-Lambda {binding} -> ... : {ty:?}");
+                println!("{prefix}+-Lambda {binding} -> ... : {ty}");
                 println!("{prefix}  |");
                 body.display_tree_(&format!("{prefix}  "));
             }
@@ -71,7 +301,7 @@ impl TypedIr {
                 e2,
                 ty,
             } => {
-                println!("{prefix}+-Let {binding} = ... in ... : {ty:?}");
+                println!("{prefix}+-Let {binding} = ... in ... : {ty}");
                 println!("{prefix}  |  |");
                 e1.display_tree_(&format!("{prefix}  |  "));
                 println!("{prefix}  |");
@@ -79,7 +309,7 @@ impl TypedIr {
             }
             Add(e1, e2) => {
                 let ty = e1.ty();
-                println!("{prefix}+-Add : {ty:?}");
+                println!("{prefix}+-Add : {ty}");
                 println!("{prefix}  |  |");
                 e1.display_tree_(&format!("{prefix}  |  "));
                 println!("{prefix}  |");
@@ -87,12 +317,48 @@ impl TypedIr {
             }
             Seq(e1, e2) => {
                 let ty = e2.ty();
-                println!("{prefix}+-Seq : {ty:?}");
+                println!("{prefix}+-Seq : {ty}");
                 println!("{prefix}  |  |");
                 e1.display_tree_(&format!("{prefix}  |  "));
                 println!("{prefix}  |");
                 e2.display_tree_(&format!("{prefix}  "));
             }
+            Annot { expr, ty } => {
+                println!("{prefix}+-Annot ... : {ty}");
+                println!("{prefix}  |");
+                expr.display_tree_(&format!("{prefix}  "));
+            }
+            Data { constructors, e2 } => {
+                let names = constructors
+                    .iter()
+                    .map(|(name, _)| *name)
+                    .collect::<Vec<_>>();
+                println!("{prefix}+-Data {} in ... : {}", names.join(" | "), e2.ty());
+                println!("{prefix}  |");
+                e2.display_tree_(&format!("{prefix}  "));
+            }
+            List(elements, ty) => {
+                println!("{prefix}+-List : {ty}");
+                for element in elements {
+                    println!("{prefix}  |");
+                    element.display_tree_(&format!("{prefix}  |"));
+                }
+            }
+            Match {
+                scrutinee,
+                arms,
+                ty,
+            } => {
+                println!("{prefix}+-Match : {ty}");
+                println!("{prefix}  |  |");
+                scrutinee.display_tree_(&format!("{prefix}  |  "));
+                for (pattern, arm) in arms {
+                    println!("{prefix}  |");
+                    println!("{prefix}  +-{pattern} -> ...");
+                    println!("{prefix}  |  |");
+                    arm.display_tree_(&format!("{prefix}  |  "));
+                }
+            }
         }
     }
     pub fn to_string(&self) -> Result<String, Box<dyn std::error::Error>> {
@@ -103,18 +369,33 @@ impl TypedIr {
         let mut buf = String::new();
         let increased_indent = format!("{indent}    ");
         match self {
-            Nop(ty) => write!(buf, "NOP:{ty:?}")?,
-            LiteralStr(s, ty) => write!(buf, "\"{s}\":{ty:?}")?,
-            LiteralInt(i, ty) => write!(buf, "{i}:{ty:?}")?,
-            Id(s, ty) => write!(buf, "{s}:{ty:?}")?,
+            Located(_, inner) => write!(buf, "{}", inner.to_string_(indent)?)?,
+            Nop(ty) => write!(buf, "NOP:{ty}")?,
+            LiteralStr(s, ty) => write!(buf, "\"{s}\":{ty}")?,
+            LiteralInt(i, ty) => write!(buf, "{i}:{ty}")?,
+            Id(s, ty) => write!(buf, "{s}:{ty}")?,
+            Hole(name, ty) => write!(buf, "{}:{ty}", hole_text(*name))?,
             App { e1, e2, ty } => {
-                write!(buf, "{} ( {} ):{ty:?}", e1.to_string()?, e2.to_string()?,)?;
+                write!(buf, "{} ( {} ):{ty}", e1.to_string()?, e2.to_string()?)?;
             }
             Lam { binding, body, ty } => {
+                // print nested lambdas in their curried form, `lambda x y -> ...`
+                let mut bindings = vec![*binding];
+                let mut body = &**body;
+                while let Lam {
+                    binding,
+                    body: inner,
+                    ..
+                } = body
+                {
+                    bindings.push(binding);
+                    body = inner;
+                }
+                let bindings = bindings.join(" ");
                 let body = body.to_string_(&increased_indent)?;
                 write!(
                     buf,
-                    "lambda {binding} -> {{\n{increased_indent}{body}\n{indent}}} : {ty:?}"
+                    "lambda {bindings} -> {{\n{increased_indent}{body}\n{indent}}} : {ty}"
                 )?;
             }
             Let {
@@ -125,19 +406,50 @@ impl TypedIr {
             } => {
                 let e1 = e1.to_string_(&increased_indent)?;
                 let e2 = e2.to_string_(&increased_indent)?;
-                write!(buf,"let {binding} = {{\n{increased_indent}{e1}\n{indent}}} in {{\n{increased_indent}{e2}\n{indent}}} : {ty:?}")?;
+                write!(buf,"let {binding} = {{\n{increased_indent}{e1}\n{indent}}} in {{\n{increased_indent}{e2}\n{indent}}} : {ty}")?;
             }
             Add(e1, e2) => {
                 let ty = e1.ty();
                 let e1 = e1.to_string()?;
                 let e2 = e2.to_string()?;
-                write!(buf, "({e1} + {e2} : {ty:?})")?;
+                write!(buf, "({e1} + {e2} : {ty})")?;
             }
             Seq(e1, e2) => {
                 let ty = e2.ty();
                 let e1 = e1.to_string()?;
                 let e2 = e2.to_string_(indent)?;
-                write!(buf, "{e1}\n{indent}{e2}\n{indent}: {ty:?}")?;
+                write!(buf, "{e1}\n{indent}{e2}\n{indent}: {ty}")?;
+            }
+            Annot { expr, ty } => {
+                let expr = expr.to_string_(indent)?;
+                write!(buf, "({expr} : {ty})")?;
+            }
+            Data { constructors, e2 } => {
+                for (name, (_, ty)) in constructors {
+                    writeln!(buf, "data {name} : {ty}")?;
+                    write!(buf, "{indent}")?;
+                }
+                write!(buf, "{}", e2.to_string_(indent)?)?;
+            }
+            List(elements, ty) => {
+                let elements = elements
+                    .iter()
+                    .map(|element| element.to_string_(indent))
+                    .collect::<Result<Vec<_>, _>>()?;
+                write!(buf, "[{}]:{ty}", elements.join(", "))?;
+            }
+            Match {
+                scrutinee,
+                arms,
+                ty,
+            } => {
+                let scrutinee = scrutinee.to_string_(indent)?;
+                writeln!(buf, "match {scrutinee} {{")?;
+                for (pattern, arm) in arms {
+                    let arm = arm.to_string_(&increased_indent)?;
+                    writeln!(buf, "{increased_indent}{pattern} -> {arm}")?;
+                }
+                write!(buf, "{indent}}} : {ty}")?;
             }
         }
         Ok(buf)
@@ -148,7 +460,7 @@ impl ApplySubst for TypedIr {
         use TypedIr::*;
         match self {
             Nop(_) | LiteralInt(_, _) | LiteralStr(_, _) => (),
-            Id(_, ty) => ty.apply_subst(subs),
+            Id(_, ty) | Hole(_, ty) => ty.apply_subst(subs),
             App { e1, e2, ty }
             | Let {
                 e1,
@@ -164,7 +476,8 @@ impl ApplySubst for TypedIr {
                 binding: _,
                 body,
                 ty,
-            } => {
+            }
+            | Annot { expr: body, ty } => {
                 body.apply_subst(subs);
                 ty.apply_subst(subs);
             }
@@ -172,6 +485,24 @@ impl ApplySubst for TypedIr {
                 lhs.apply_subst(subs);
                 rhs.apply_subst(subs);
             }
+            Data { e2, .. } | Located(_, e2) => e2.apply_subst(subs),
+            List(elements, ty) => {
+                for element in elements {
+                    element.apply_subst(subs);
+                }
+                ty.apply_subst(subs);
+            }
+            Match {
+                scrutinee,
+                arms,
+                ty,
+            } => {
+                scrutinee.apply_subst(subs);
+                for (_, arm) in arms {
+                    arm.apply_subst(subs);
+                }
+                ty.apply_subst(subs);
+            }
         }
     }
 }
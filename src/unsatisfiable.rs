@@ -0,0 +1,69 @@
+//! Explaining why constraints have no solution.
+//!
+//! `solve` fails on whichever constraint happens to contradict the ones it
+//! solved before, which says little about where the mistake is. A minimal
+//! unsatisfiable subset is a set of constraints that can't all hold, but
+//! would if any one of them were dropped, so every part of the program it
+//! comes from plays a part in the error.
+
+use crate::{solve, Constraint, Constraints, Span, TypeError};
+
+/// A minimal set of `constraints` that can't all be satisfied, each with
+/// the source expression it was generated for, if known: leaving out any
+/// one of them lets the rest be satisfied. `None` if `constraints` can be
+/// satisfied.
+///
+/// Found by deletion: each constraint in turn is left out, and stays out if
+/// the rest still can't be satisfied. That takes one `solve` per
+/// constraint, so it's meant for explaining an error after it's been found.
+/// It's minimal, not minimum: a different order of deletion might find a
+/// set with fewer members
+#[must_use]
+pub fn minimal_unsatisfiable_subset(
+    constraints: &Constraints,
+) -> Option<Vec<(Constraint, Option<Span>)>> {
+    let mut members = constraints.iter().collect::<Vec<_>>();
+    // in source order, so the same program gets the same explanation
+    members.sort_by_cached_key(|c| {
+        (
+            constraints.origin(c).map(|s| (s.start, s.end)),
+            c.to_string(),
+        )
+    });
+    if is_satisfiable(&members) {
+        return None;
+    }
+    let mut i = 0;
+    while i < members.len() {
+        let removed = members.remove(i);
+        if is_satisfiable(&members) {
+            members.insert(i, removed);
+            i += 1;
+        }
+    }
+    Some(
+        (members.into_iter())
+            .map(|c| {
+                let origin = constraints.origin(&c);
+                (c, origin)
+            })
+            .collect(),
+    )
+}
+
+/// whether `solve` finds a solution.
+///
+/// `solve` only gives up with `CircularGeneralization` once it has unified
+/// every equality and instantiated every explicit instance without finding
+/// a contradiction, and is left with implicit instances that each wait on
+/// another. Dropping constraints from a program's set can leave it like
+/// that. Counting it as satisfiable means a constraint is only left out of
+/// the subset when the rest still contradict each other, so the subset is
+/// always one `solve` rejects with a real type error, never one it just
+/// couldn't put in order
+fn is_satisfiable(constraints: &[Constraint]) -> bool {
+    match solve(constraints.iter().cloned().collect()) {
+        Ok(_) | Err(TypeError::CircularGeneralization) => true,
+        Err(_) => false,
+    }
+}
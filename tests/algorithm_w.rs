@@ -0,0 +1,99 @@
+use heeren_hage_swierstra::{
+    algorithm_w, default_environment, infer_type, parse_expr, scheme_to_string, FreeVars, Ir, Type,
+};
+
+/// expressions that typecheck against `default_environment`, with their
+/// principal types
+const WELL_TYPED: &[(&str, &str)] = &[
+    ("1 + len \"four\"", "Int"),
+    ("\\x -> x", "forall a. a -> a"),
+    (
+        "\\f g x -> f (g x)",
+        "forall a b c. (a -> b) -> (c -> a) -> c -> b",
+    ),
+    ("\\f x -> f (f x)", "forall a. (a -> a) -> a -> a"),
+    ("let k = \\x y -> x in k 1", "forall a. a -> Int"),
+    ("let id = \\x -> x in id id", "forall a. a -> a"),
+    ("let id = \\x -> x in do { print (id \"s\"); id 3 }", "Int"),
+    ("\\x -> let y = x in y", "forall a. a -> a"),
+    ("\\x -> let f = \\y -> x in f", "forall a b. a -> b -> a"),
+    ("\\f -> let x = f 1 in x", "forall a. (Int -> a) -> a"),
+    ("let r = ref [] in r", "forall a. Ref (List a)"),
+    (
+        "let r = ref (\\x -> x) in do { r := (\\x -> x + 1); !r }",
+        "Int -> Int",
+    ),
+    ("\\s -> do { print s; len s }", "Str -> Int"),
+    ("map (\\x -> x + 1) [1, 2, 3]", "List Int"),
+    ("fold (\\acc x -> acc + x) 0", "List Int -> Int"),
+    ("filter (\\x -> true)", "forall a. List a -> List a"),
+    ("[[1], []]", "List (List Int)"),
+    ("[]", "forall a. List a"),
+    (
+        "\\xs -> match xs { [] -> 0; x :: rest -> x }",
+        "List Int -> Int",
+    ),
+    (
+        "\\l -> match l { a :: b :: _ -> a + b; _ -> 0 }",
+        "List Int -> Int",
+    ),
+    ("\\x -> match x { y -> y }", "forall a. a -> a"),
+    ("\\x -> (x : Int)", "Int -> Int"),
+    (
+        "do { data Pair a b = MkPair a b; MkPair 1 }",
+        "forall a. a -> Pair Int a",
+    ),
+];
+
+/// expressions that don't typecheck against `default_environment`
+const ILL_TYPED: &[&str] = &[
+    "1 + \"s\"",
+    "\\x -> x x",
+    "\\x -> x + len x",
+    "[1, \"two\"]",
+    "match 1 { [] -> 0 }",
+    "(\\x -> x : Int -> Str)",
+    "unbound",
+    "let r = ref [] in do { r := [1]; r := [\"s\"] }",
+    "\\f -> f 1 + len (f \"s\")",
+    "\\xs -> match xs { x :: rest -> x + rest }",
+];
+
+fn ir(source: &str) -> Ir {
+    parse_expr(source)
+        .unwrap_or_else(|e| panic!("`{source}` doesn't parse: {e}"))
+        .desugar()
+        .unwrap_or_else(|e| panic!("`{source}` doesn't desugar: {e}"))
+}
+
+/// `ty` written the same way as any type that differs from it only in the
+/// names of its type variables
+fn canonical(ty: &Type) -> String {
+    scheme_to_string(&(ty.free_vars(), ty.clone()))
+}
+
+#[test]
+fn infer_type_agrees_with_algorithm_w() {
+    let environment = default_environment();
+    for (source, expected) in WELL_TYPED {
+        let (_, typed) = infer_type(&environment, ir(source))
+            .unwrap_or_else(|e| panic!("infer_type rejects `{source}`: {e}"));
+        let (_, reference) = algorithm_w(&environment, &ir(source))
+            .unwrap_or_else(|e| panic!("algorithm W rejects `{source}`: {e}"));
+        assert_eq!(canonical(typed.ty()), canonical(&reference), "{source}");
+        assert_eq!(canonical(&reference), *expected, "{source}");
+    }
+}
+
+#[test]
+fn infer_type_rejects_what_algorithm_w_rejects() {
+    let environment = default_environment();
+    for source in ILL_TYPED {
+        if let Ok((_, typed)) = infer_type(&environment, ir(source)) {
+            panic!("infer_type accepts `{source}` as {}", typed.ty());
+        }
+        if let Ok((_, ty)) = algorithm_w(&environment, &ir(source)) {
+            panic!("algorithm W accepts `{source}` as {ty}");
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use std::{
+    process::Command,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// run the checker on a file containing `source`, returning whether it
+/// succeeded and what it printed
+fn check(source: &str) -> (bool, String, String) {
+    // tests run at the same time, so each needs a file of its own
+    static FILES: AtomicUsize = AtomicUsize::new(0);
+    let file = FILES.fetch_add(1, Ordering::Relaxed);
+    let name = format!("cli_{}_{file}.hhs", std::process::id());
+    let path = std::env::temp_dir().join(name);
+    std::fs::write(&path, source).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_heeren_hage_swierstra"))
+        .arg(&path)
+        .output()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+    (
+        output.status.success(),
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+#[test]
+fn names_local_to_a_trailing_expression_are_not_printed() {
+    assert_eq!(
+        check("let x = 1 in x + 1"),
+        (true, String::new(), String::new())
+    );
+    assert_eq!(
+        check("do { let a = 1; a + 1 }"),
+        (true, String::new(), String::new())
+    );
+    assert_eq!(
+        check("let n = 1\nlet k = 2 in k + n"),
+        (true, "n : Int\n".to_owned(), String::new())
+    );
+}
+
+#[test]
+fn type_errors_are_located() {
+    let (success, stdout, stderr) = check("let n = 1\nlet s = len n");
+    assert!(!success);
+    assert_eq!(stdout, "");
+    assert!(
+        stderr.contains(".hhs:1:9: error: unable to unify types: "),
+        "{stderr}"
+    );
+}
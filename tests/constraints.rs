@@ -0,0 +1,71 @@
+use heeren_hage_swierstra::{default_environment, generate_constraints, parse};
+
+/// the constraints of `source`, one per line in the order they're printed,
+/// with type variables numbered from `t0` in the order of their ids, since
+/// the ids themselves depend on what ran before
+fn constraints(source: &str) -> Vec<String> {
+    let environment = default_environment();
+    let ir = parse(source).unwrap().desugar_program().unwrap();
+    let printed = generate_constraints(&environment, ir)
+        .unwrap()
+        .0
+        .to_string();
+    let mut ids = Vec::new();
+    let words = printed.split(|c: char| !c.is_alphanumeric());
+    for id in words.filter_map(|word| word.strip_prefix('t')?.parse::<u32>().ok()) {
+        ids.push(id);
+    }
+    ids.sort_unstable();
+    ids.dedup();
+    let mut lines = (printed.lines())
+        .map(|line| {
+            let mut renumbered = String::new();
+            let mut rest = line;
+            while let Some(i) = rest.find('t') {
+                let (before, from_t) = rest.split_at(i);
+                renumbered.push_str(before);
+                let digits = from_t[1..]
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(from_t.len() - 1);
+                let follows_a_name = before.ends_with(char::is_alphanumeric);
+                match from_t[1..=digits].parse::<u32>() {
+                    Ok(id) if !follows_a_name => {
+                        let rank = ids.binary_search(&id).unwrap();
+                        renumbered.push_str(&format!("t{rank}"));
+                        rest = &from_t[1 + digits..];
+                    }
+                    _ => {
+                        renumbered.push('t');
+                        rest = &from_t[1..];
+                    }
+                }
+            }
+            renumbered.push_str(rest);
+            renumbered
+        })
+        .collect::<Vec<_>>();
+    lines.sort();
+    lines
+}
+
+#[test]
+fn constraints_are_written_in_the_papers_notation() {
+    assert_eq!(
+        constraints("let id = \\x -> x in id 1"),
+        ["t1 ≡ t0", "t2 ≡ Int -> t3", "t2 ≤_{} t0 -> t1"]
+    );
+    assert_eq!(
+        constraints("let f = \\y -> let id = \\x -> x in map id [id y]"),
+        [
+            "t10 ≡ t11",
+            "t4 ≡ t3",
+            "t5 ≡ t6 -> t7",
+            "t5 ≤ ∀t0 t1. (t0 -> t1) -> List t0 -> List t1",
+            "t6 ≤_{t2} t3 -> t4",
+            "t7 ≡ List t11 -> t12",
+            "t8 ≡ t9 -> t10",
+            "t8 ≤_{t2} t3 -> t4",
+            "t9 ≡ t2",
+        ]
+    );
+}
@@ -0,0 +1,60 @@
+use std::process::Command;
+
+use heeren_hage_swierstra::{
+    check_discarded_values, default_environment, infer_type, parse_expr, DiscardedValue, Span, Type,
+};
+
+fn discarded(source: &str) -> Vec<DiscardedValue> {
+    let ir = parse_expr(source).unwrap().desugar().unwrap();
+    let (_, typed) = infer_type(&default_environment(), ir).unwrap();
+    check_discarded_values(&typed)
+}
+
+#[test]
+fn values_thrown_away_by_statements_are_reported() {
+    let source = "do { 1 + 2; print 3; len \"four\" }";
+    assert_eq!(
+        discarded(source),
+        [DiscardedValue {
+            span: Some(Span::new(5, 10)),
+            ty: Type::Int,
+        }]
+    );
+}
+
+#[test]
+fn nothing_the_last_statement_and_unknown_types_are_not_reported() {
+    assert!(discarded("do { print 1; print 2; 3 }").is_empty());
+    assert!(discarded("\\f -> do { f 1; 2 }").is_empty());
+    assert!(discarded("1 + 2").is_empty());
+}
+
+/// run the checker on a file containing `source`, returning what it writes
+/// to stderr
+fn check(source: &str, flags: &[&str]) -> String {
+    let path = std::env::temp_dir().join(format!("discarded_{}.hhs", std::process::id()));
+    std::fs::write(&path, source).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_heeren_hage_swierstra"))
+        .args(flags)
+        .arg(&path)
+        .output()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(output.status.success(), "{output:?}");
+    String::from_utf8(output.stderr).unwrap()
+}
+
+#[test]
+fn the_checker_warns_about_discarded_values_when_asked() {
+    let source = "let x = do {\n  len \"s\"\n  print 1\n}";
+    let warnings = check(source, &["--warn-discarded"]);
+    assert!(
+        warnings.ends_with(":2:3: warning: discarded value of type Int\n"),
+        "{warnings}"
+    );
+    assert_eq!(check(source, &[]), "");
+    assert_eq!(
+        check("let x = do { print 1; 2 }", &["--warn-discarded"]),
+        ""
+    );
+}
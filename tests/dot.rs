@@ -0,0 +1,69 @@
+use std::collections::HashSet;
+
+use heeren_hage_swierstra::{default_environment, generate_constraints, parse_expr};
+
+/// the ids of the nodes `dot` declares and the pairs of ids its edges link,
+/// checking that every edge is between declared nodes
+fn graph(dot: &str, name: &str) -> (HashSet<String>, Vec<(String, String)>) {
+    let body = dot
+        .strip_prefix(&format!("digraph {name} {{\n"))
+        .and_then(|rest| rest.strip_suffix("}\n"))
+        .unwrap_or_else(|| panic!("not a digraph called {name}:\n{dot}"));
+    let mut nodes = HashSet::new();
+    let mut edges = Vec::new();
+    for line in body.lines().map(str::trim) {
+        let statement = line.split(" [").next().unwrap();
+        if let Some((from, to)) = statement.split_once(" -> ") {
+            edges.push((from.to_owned(), to.to_owned()));
+        } else if statement != "node" {
+            assert!(
+                nodes.insert(statement.to_owned()),
+                "{statement} declared twice"
+            );
+        }
+    }
+    for (from, to) in &edges {
+        assert!(nodes.contains(from) && nodes.contains(to), "{from} -> {to}");
+    }
+    (nodes, edges)
+}
+
+#[test]
+fn syntax_trees_are_written_as_trees() {
+    let ast = parse_expr("(\\x -> x) 1").unwrap();
+    assert_eq!(
+        ast.to_dot(),
+        "digraph ast {
+    node [fontname=monospace];
+    n0 [label=\"App\", shape=box];
+    n1 [label=\"λ x\", shape=box];
+    n2 [label=\"x\", shape=box];
+    n1 -> n2 [];
+    n0 -> n1 [];
+    n3 [label=\"1\", shape=box];
+    n0 -> n3 [];
+}
+"
+    );
+    let ir = ast.desugar().unwrap();
+    let (nodes, edges) = graph(&ir.to_dot(), "ir");
+    assert_eq!((nodes.len(), edges.len()), (4, 3));
+    let (_, typed) = generate_constraints(&default_environment(), ir).unwrap();
+    let (nodes, edges) = graph(&typed.to_dot(), "typed");
+    assert_eq!((nodes.len(), edges.len()), (4, 3));
+}
+
+#[test]
+fn constraint_sets_have_an_edge_per_constraint() {
+    let ir = parse_expr("\\f -> f (len \"s\") + 1")
+        .unwrap()
+        .desugar()
+        .unwrap();
+    let (constraints, _) = generate_constraints(&default_environment(), ir).unwrap();
+    let dot = constraints.to_dot();
+    graph(&dot, "constraints");
+    let relations = (dot.lines())
+        .filter(|line| line.contains("label=\"≡\"") || line.contains("label=\"≤"))
+        .count();
+    assert_eq!(relations, constraints.iter().count(), "{dot}");
+}
@@ -0,0 +1,190 @@
+use heeren_hage_swierstra::{
+    default_environment, default_runtime_environment, infer_type, Ast, EvalError, Interpreter,
+    Pattern, TypeExpr,
+};
+
+fn id(name: &'static str) -> Ast {
+    Ast::Id(name)
+}
+fn int(i: i64) -> Ast {
+    Ast::LiteralInt(i)
+}
+fn app(f: Ast, args: impl IntoIterator<Item = Ast>) -> Ast {
+    args.into_iter().fold(f, |f, arg| Ast::App {
+        e1: f.into(),
+        e2: arg.into(),
+    })
+}
+fn lam(bindings: &[&'static str], body: Ast) -> Ast {
+    Ast::Lam {
+        bindings: bindings.to_vec(),
+        body: body.into(),
+    }
+}
+fn add(lhs: Ast, rhs: Ast) -> Ast {
+    Ast::Add(lhs.into(), rhs.into())
+}
+fn let_(binding: &'static str, e1: Ast) -> Ast {
+    Ast::Let {
+        e1: e1.into(),
+        binding,
+    }
+}
+
+/// programs that typecheck against `default_environment`
+fn well_typed_programs() -> Vec<Ast> {
+    vec![
+        add(int(1), app(id("len"), [Ast::LiteralStr("four")])),
+        Ast::Do(vec![
+            let_("i", lam(&["x"], id("x"))),
+            app(
+                id("print"),
+                [app(id("i"), [Ast::LiteralStr("polymorphic")])],
+            ),
+            app(id("i"), [int(3)]),
+        ]),
+        Ast::Do(vec![
+            let_("r", app(id("ref"), [int(1)])),
+            app(id(":="), [id("r"), add(app(id("!"), [id("r")]), int(41))]),
+            app(id("!"), [id("r")]),
+        ]),
+        Ast::Do(vec![
+            let_("xs", Ast::List(vec![int(1), int(2), int(3)])),
+            let_(
+                "ys",
+                app(id("map"), [lam(&["x"], add(id("x"), int(1))), id("xs")]),
+            ),
+            let_(
+                "evens",
+                app(id("filter"), [lam(&["_x"], id("true")), id("ys")]),
+            ),
+            app(
+                id("fold"),
+                [
+                    lam(&["acc", "x"], add(id("acc"), id("x"))),
+                    int(0),
+                    id("evens"),
+                ],
+            ),
+        ]),
+        Ast::LetIn {
+            bindings: vec![(
+                "sum2",
+                lam(
+                    &["l"],
+                    Ast::Match {
+                        scrutinee: id("l").into(),
+                        arms: vec![
+                            (
+                                Pattern::Cons(
+                                    Pattern::Var("a").into(),
+                                    Pattern::Cons(
+                                        Pattern::Var("b").into(),
+                                        Pattern::Wildcard.into(),
+                                    )
+                                    .into(),
+                                ),
+                                add(id("a"), id("b")),
+                            ),
+                            (Pattern::Wildcard, int(0)),
+                        ],
+                    },
+                ),
+            )],
+            body: add(
+                app(id("sum2"), [Ast::List(vec![int(5), int(6), int(7)])]),
+                app(id("sum2"), [Ast::List(vec![])]),
+            )
+            .into(),
+        },
+        Ast::Do(vec![
+            Ast::Data {
+                name: "Pair",
+                params: vec!["a", "b"],
+                constructors: vec![("MkPair", vec![TypeExpr::Named("a"), TypeExpr::Named("b")])],
+            },
+            let_("p", app(id("MkPair"), [int(1), Ast::LiteralStr("one")])),
+            app(id("print"), [id("p")]),
+        ]),
+        Ast::Do(vec![
+            Ast::Fn {
+                fn_name: "compose",
+                parameters: vec!["f", "g", "x"],
+                body: app(id("f"), [app(id("g"), [id("x")])]).into(),
+            },
+            app(
+                id("compose"),
+                [
+                    id("length"),
+                    lam(&["s"], Ast::List(vec![id("s"), id("s")])),
+                    int(0),
+                ],
+            ),
+        ]),
+    ]
+}
+
+#[test]
+fn well_typed_programs_do_not_get_stuck() {
+    for ast in well_typed_programs() {
+        let ir = ast.desugar().unwrap();
+        let (_, typed) = infer_type(&default_environment(), ir).unwrap();
+        let ir = typed.erase();
+        let mut out = Vec::new();
+        let result = Interpreter::new(&mut out).eval(&default_runtime_environment(), &ir);
+        assert!(
+            result.is_ok(),
+            "{} got {}",
+            typed.to_string().unwrap(),
+            result.err().unwrap()
+        );
+    }
+}
+
+#[test]
+fn ill_typed_program_gets_stuck() {
+    let ir = add(int(1), Ast::LiteralStr("one")).desugar().unwrap();
+    let mut out = Vec::new();
+    let result = Interpreter::new(&mut out).eval(&default_runtime_environment(), &ir);
+    assert!(matches!(result, Err(EvalError::Stuck(_))));
+    let ir = add(int(1), Ast::LiteralStr("one")).desugar().unwrap();
+    assert!(infer_type(&default_environment(), ir).is_err());
+}
+
+#[test]
+fn evaluates_closures_and_refs() {
+    let ast = Ast::Do(vec![
+        let_("counter", app(id("ref"), [int(0)])),
+        let_(
+            "bump",
+            lam(
+                &["n"],
+                app(
+                    id(":="),
+                    [id("counter"), add(app(id("!"), [id("counter")]), id("n"))],
+                ),
+            ),
+        ),
+        app(id("bump"), [int(2)]),
+        app(id("bump"), [int(3)]),
+        app(id("print"), [app(id("!"), [id("counter")])]),
+        app(id("print"), [Ast::LiteralStr("done")]),
+    ]);
+    let ir = ast.desugar().unwrap();
+    let mut out = Vec::new();
+    Interpreter::new(&mut out)
+        .eval(&default_runtime_environment(), &ir)
+        .unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "5\ndone\n");
+}
+
+#[test]
+fn runtime_environment_mirrors_typing_environment() {
+    let mut typed = default_environment().into_keys().collect::<Vec<_>>();
+    let mut runtime = default_runtime_environment()
+        .into_keys()
+        .collect::<Vec<_>>();
+    typed.sort_unstable();
+    runtime.sort_unstable();
+    assert_eq!(typed, runtime);
+}
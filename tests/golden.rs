@@ -0,0 +1,118 @@
+//! Golden-file tests: each program in `tests/golden` is checked against the
+//! `.out` file next to it, which holds the schemes of its top-level
+//! bindings, or the errors it's rejected with. Programs whose first line is
+//! `-- golden: typed` also have their typed tree written out.
+//!
+//! After a change that's meant to alter the output, run
+//! `BLESS=1 cargo test --test golden` to rewrite the `.out` files, and
+//! review the diff.
+
+use std::{collections::HashMap, fmt::Write, fs, path::Path};
+
+use heeren_hage_swierstra::{
+    default_environment, infer_type, parse, scheme_to_string, Span, TypeError,
+};
+
+/// what the parser and `infer_type` make of `source`
+fn run(source: &str) -> String {
+    let location = |span: Span| {
+        let (line, col) = span.line_col(source);
+        format!("{line}:{col}")
+    };
+    let ast = match parse(source) {
+        Ok(ast) => ast,
+        Err(e) => return format!("{}: error: {e}\n", location(e.span)),
+    };
+    let environment = default_environment();
+    let inferred = ast
+        .desugar_program()
+        .and_then(|ir| infer_type(&environment, ir));
+    let mut out = String::new();
+    match inferred {
+        Ok((_, typed)) => {
+            for (name, scheme) in typed.top_level_bindings(&environment) {
+                writeln!(out, "{name} : {}", scheme_to_string(&scheme)).unwrap();
+            }
+            if source.starts_with("-- golden: typed") {
+                writeln!(out, "\n{}", typed.to_string().unwrap()).unwrap();
+            }
+        }
+        Err(TypeError::Holes(holes)) => {
+            for hole in holes {
+                let at = hole.span.map(|span| format!("{}: ", location(span)));
+                writeln!(out, "{}error: {hole}", at.unwrap_or_default()).unwrap();
+            }
+        }
+        Err(e) => writeln!(out, "error: {e}").unwrap(),
+    }
+    out
+}
+
+/// `output` with its type variables numbered by first appearance, since
+/// the ids inference gives them depend on what ran before
+fn renumber(output: &str) -> String {
+    let mut numbers = HashMap::new();
+    let mut renumbered = String::new();
+    let mut rest = output;
+    while let Some(i) = rest.find('t') {
+        let (before, from_t) = rest.split_at(i);
+        renumbered.push_str(before);
+        let digits = from_t[1..]
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(from_t.len() - 1);
+        let starts_word = !renumbered.ends_with(|c: char| c.is_alphanumeric() || c == '_');
+        let ends_word =
+            !from_t[1 + digits..].starts_with(|c: char| c.is_alphanumeric() || c == '_');
+        if digits > 0 && starts_word && ends_word {
+            let next = numbers.len();
+            let number = *numbers.entry(&from_t[1..=digits]).or_insert(next);
+            write!(renumbered, "t{number}").unwrap();
+        } else {
+            renumbered.push_str(&from_t[..=digits]);
+        }
+        rest = &from_t[1 + digits..];
+    }
+    renumbered.push_str(rest);
+    renumbered
+}
+
+#[test]
+fn golden_files() {
+    let bless = std::env::var_os("BLESS").is_some();
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    let mut programs = fs::read_dir(&directory)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|e| e == "hhs"))
+        .collect::<Vec<_>>();
+    programs.sort();
+    assert!(
+        !programs.is_empty(),
+        "no programs in {}",
+        directory.display()
+    );
+
+    let mut failures = Vec::new();
+    for program in &programs {
+        let source = fs::read_to_string(program).unwrap();
+        let actual = renumber(&run(&source));
+        let expected_path = program.with_extension("out");
+        if bless {
+            fs::write(&expected_path, &actual).unwrap();
+            continue;
+        }
+        let name = program.file_name().unwrap().to_string_lossy();
+        match fs::read_to_string(&expected_path) {
+            Ok(expected) if expected == actual => (),
+            Ok(expected) => failures.push(format!(
+                "{name}: output differs\n--- expected\n{expected}--- actual\n{actual}"
+            )),
+            Err(_) => failures.push(format!("{name}: no .out file\n--- actual\n{actual}")),
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "{}\n\nrun `BLESS=1 cargo test --test golden` if these changes are intended",
+        failures.join("\n")
+    );
+}
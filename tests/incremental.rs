@@ -0,0 +1,91 @@
+use heeren_hage_swierstra::{parse, Checked, IncrementalInference, Span, TypedIr};
+
+fn check(inference: &mut IncrementalInference, source: &str) -> Checked {
+    let program = parse(source).unwrap().desugar_program().unwrap();
+    inference.check(source, program).unwrap()
+}
+
+/// every location in `typed`, outermost first
+fn spans(typed: &TypedIr) -> Vec<Span> {
+    let mut spans = Vec::from_iter(typed.span());
+    for child in typed.children() {
+        spans.extend(self::spans(child));
+    }
+    spans
+}
+
+/// where the constraints for `name` were generated, sorted
+fn origins(inference: &IncrementalInference, name: &str) -> Vec<Option<Span>> {
+    let constraints = inference.constraints(name).unwrap();
+    let mut origins = (constraints.iter())
+        .map(|constraint| constraints.origin(&constraint))
+        .collect::<Vec<_>>();
+    origins.sort_unstable_by_key(|span| span.map(|span| (span.start, span.end)));
+    origins
+}
+
+#[test]
+fn unchanged_statements_come_from_the_cache() {
+    let mut inference = IncrementalInference::default();
+    let source = "let id = \\x -> x\nlet n = id 1\nlet s = id \"s\"";
+    assert_eq!(check(&mut inference, source).reinferred, [0, 1, 2]);
+    assert!(check(&mut inference, source).reinferred.is_empty());
+    let edited = "let id = \\x -> x\nlet n = id 2\nlet s = id \"s\"";
+    let checked = check(&mut inference, edited);
+    assert_eq!(checked.reinferred, [1]);
+    let bindings = (checked.bindings.iter())
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>();
+    assert_eq!(bindings, ["id", "n", "s"]);
+}
+
+#[test]
+fn dependents_are_only_reinferred_when_a_scheme_changes() {
+    let mut inference = IncrementalInference::default();
+    check(
+        &mut inference,
+        "let f = \\x -> x + 1\nlet a = f 1\nlet b = 2",
+    );
+    let same_scheme = check(
+        &mut inference,
+        "let f = \\x -> x + 2\nlet a = f 1\nlet b = 2",
+    );
+    assert_eq!(same_scheme.reinferred, [0]);
+    let new_scheme = check(&mut inference, "let f = \\x -> x\nlet a = f 1\nlet b = 2");
+    assert_eq!(new_scheme.reinferred, [0, 1]);
+}
+
+#[test]
+fn statements_declaring_data_types_are_cached() {
+    let mut inference = IncrementalInference::default();
+    let source = "let n = do { data T = A | B; 1 }\ndata U = C\nlet c = C";
+    assert_eq!(check(&mut inference, source).reinferred, [0, 1, 2]);
+    assert!(check(&mut inference, source).reinferred.is_empty());
+}
+
+#[test]
+fn annotations_are_reinferred_when_an_alias_changes() {
+    let mut inference = IncrementalInference::default();
+    let source = "type Name = Str\nlet greet = \\n -> (n : Name)\nlet g = greet";
+    check(&mut inference, source);
+    let checked = check(&mut inference, &source.replace("Str", "Int"));
+    assert_eq!(checked.reinferred, [0, 1]);
+}
+
+#[test]
+fn cached_statements_move_with_their_text() {
+    let source = "let n = 1\nlet m = n + 2\nprint m";
+    let moved = "let n = 1\n\n\nlet m  =  n + 2\n  print m";
+    let mut inference = IncrementalInference::default();
+    check(&mut inference, source);
+    let checked = check(&mut inference, &format!("\n\n{source}"));
+    assert!(checked.reinferred.is_empty());
+    let checked = check(&mut inference, moved);
+    assert_eq!(checked.reinferred, [1]);
+
+    let mut fresh = IncrementalInference::default();
+    let expected = check(&mut fresh, moved);
+    assert_eq!(spans(&checked.typed), spans(&expected.typed));
+    assert_eq!(origins(&inference, "n"), origins(&fresh, "n"));
+    assert_eq!(origins(&inference, "m"), origins(&fresh, "m"));
+}
@@ -0,0 +1,140 @@
+use std::{cell::RefCell, collections::HashMap, io, rc::Rc};
+
+use heeren_hage_swierstra::{
+    default_environment, environment_to_string, parse_environment, scheme_to_string, Interface,
+    ModuleError, ModuleLoader,
+};
+
+type Files = Rc<RefCell<HashMap<String, String>>>;
+
+/// a loader reading module sources from `sources` and keeping interfaces
+/// in `interfaces`
+fn loader(sources: &Files, interfaces: &Files) -> ModuleLoader {
+    let sources = Rc::clone(sources);
+    let read = Rc::clone(interfaces);
+    let write = Rc::clone(interfaces);
+    ModuleLoader::new(default_environment(), move |name| {
+        (sources.borrow().get(name).cloned())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no such module"))
+    })
+    .with_interfaces(
+        move |name| read.borrow().get(name).cloned(),
+        move |name, text| {
+            write.borrow_mut().insert(name.to_owned(), text.to_owned());
+        },
+    )
+}
+
+fn files(files: &[(&str, &str)]) -> Files {
+    Rc::new(RefCell::new(
+        (files.iter())
+            .map(|(name, text)| ((*name).to_owned(), (*text).to_owned()))
+            .collect(),
+    ))
+}
+
+#[test]
+fn interfaces_read_back_what_was_written() {
+    let exports = parse_environment(
+        "map : forall a b. (a -> b) -> List a -> List b\nunit : Geometry.Shape\nn : Int",
+    )
+    .unwrap();
+    let text = Interface::new(0x5c1b_3a0e_27f1_d9e4, exports.clone()).to_string();
+    assert!(text.starts_with("-- hash 5c1b3a0e27f1d9e4\n"), "{text}");
+    let read = Interface::parse(&text).unwrap();
+    assert_eq!(read.hash, 0x5c1b_3a0e_27f1_d9e4);
+    assert_eq!(
+        environment_to_string(&read.exports),
+        environment_to_string(&exports)
+    );
+    assert!(Interface::parse("n : Int").is_err());
+}
+
+#[test]
+fn interfaces_are_used_until_the_source_changes() {
+    let sources = files(&[("Lib", "let n = 1")]);
+    let interfaces = files(&[]);
+    loader(&sources, &interfaces).load("Lib").unwrap();
+    let written = interfaces.borrow()["Lib"].clone();
+    assert!(written.ends_with("\nn : Int\n"), "{written}");
+
+    // an interface with the right hash is believed without inferring
+    let edited = written.replace("n : Int", "n : Str");
+    interfaces.borrow_mut().insert("Lib".to_owned(), edited);
+    let mut reader = loader(&sources, &interfaces);
+    let exports = &reader.load("Lib").unwrap().exports;
+    assert_eq!(environment_to_string(exports), "n : Str\n");
+
+    sources
+        .borrow_mut()
+        .insert("Lib".to_owned(), "let n = \\x -> x".to_owned());
+    let mut reader = loader(&sources, &interfaces);
+    let exports = &reader.load("Lib").unwrap().exports;
+    assert_eq!(environment_to_string(exports), "n : forall a. a -> a\n");
+    assert!(interfaces.borrow()["Lib"].ends_with("\nn : forall a. a -> a\n"));
+}
+
+#[test]
+fn interfaces_are_inferred_again_when_an_import_changes() {
+    let sources = files(&[("Lib", "let n = 1"), ("Main", "import Lib (n)\nlet m = n")]);
+    let interfaces = files(&[]);
+    loader(&sources, &interfaces).load("Main").unwrap();
+    let main = interfaces.borrow()["Main"].clone();
+
+    sources
+        .borrow_mut()
+        .insert("Lib".to_owned(), "let n = \"s\"".to_owned());
+    let mut reader = loader(&sources, &interfaces);
+    let exports = &reader.load("Main").unwrap().exports;
+    assert_eq!(environment_to_string(exports), "m : Str\n");
+    assert_ne!(interfaces.borrow()["Main"], main);
+}
+
+#[test]
+fn bindings_that_are_not_generalized_cannot_be_exported() {
+    let sources = files(&[
+        ("Refs", "let r = ref []\nlet n = 1"),
+        ("Private", "export n\nlet r = ref []\nlet n = 1"),
+    ]);
+    let interfaces = files(&[]);
+    let mut loader = loader(&sources, &interfaces);
+    assert_eq!(
+        loader.load("Refs").unwrap_err(),
+        ModuleError::WeakExport {
+            module: "Refs",
+            name: "r"
+        }
+    );
+    assert!(!interfaces.borrow().contains_key("Refs"));
+    assert!(loader.load("Private").is_ok());
+}
+
+#[test]
+fn cached_interfaces_print_the_same_schemes() {
+    let sources = files(&[
+        ("Geo", "type Name = Str\nlet name = (\"x\" : Name)"),
+        (
+            "Main",
+            "import Geo\ntype N = Int\nlet b = Geo.name\nlet m = (1 : N)",
+        ),
+    ]);
+    let interfaces = files(&[]);
+    // as the checker prints them
+    let printed = |loader: &mut ModuleLoader, module| {
+        let exports = &loader.load(module).unwrap().exports;
+        let mut lines = (exports.iter())
+            .map(|(name, scheme)| format!("{name} : {}\n", scheme_to_string(scheme)))
+            .collect::<Vec<_>>();
+        lines.sort();
+        lines.concat()
+    };
+    let mut uncached = loader(&sources, &interfaces);
+    let (geo, main) = (
+        printed(&mut uncached, "Geo"),
+        printed(&mut uncached, "Main"),
+    );
+    assert_eq!(main, "b : Str\nm : Int\n");
+    let mut cached = loader(&sources, &interfaces);
+    assert_eq!(printed(&mut cached, "Geo"), geo);
+    assert_eq!(printed(&mut cached, "Main"), main);
+}
@@ -0,0 +1,333 @@
+//! Drives the language server with a scripted client, as an editor would
+
+use std::io::Cursor;
+
+use heeren_hage_swierstra::{
+    default_environment, parse_environment, serve_language_server, Environment,
+};
+use serde_json::{json, Value};
+
+const URI: &str = "file:///test.hhs";
+
+fn frame(messages: &[Value]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for message in messages {
+        let body = message.to_string();
+        bytes.extend(format!("Content-Length: {}\r\n\r\n{body}", body.len()).bytes());
+    }
+    bytes
+}
+
+fn unframe(mut output: &str) -> Vec<Value> {
+    let mut messages = Vec::new();
+    while let Some(rest) = output.strip_prefix("Content-Length: ") {
+        let (length, rest) = rest.split_once("\r\n\r\n").unwrap();
+        let length: usize = length.parse().unwrap();
+        messages.push(serde_json::from_str(&rest[..length]).unwrap());
+        output = &rest[length..];
+    }
+    assert!(output.is_empty(), "unframed output: {output:?}");
+    messages
+}
+
+/// run a session that opens `text` and then sends `requests`, returning
+/// everything the server wrote
+fn session(text: &str, requests: &[Value]) -> Vec<Value> {
+    session_with(&default_environment(), URI, text, requests)
+}
+
+/// `session`, with `environment` in scope and `text` at `uri`
+fn session_with(
+    environment: &Environment,
+    uri: &str,
+    text: &str,
+    requests: &[Value],
+) -> Vec<Value> {
+    let mut messages = vec![
+        json!({"jsonrpc": "2.0", "id": 0, "method": "initialize", "params": {}}),
+        json!({"jsonrpc": "2.0", "method": "initialized", "params": {}}),
+        json!({"jsonrpc": "2.0", "method": "textDocument/didOpen", "params": {
+            "textDocument": {"uri": uri, "languageId": "hhs", "version": 1, "text": text}
+        }}),
+    ];
+    messages.extend_from_slice(requests);
+    messages.push(json!({"jsonrpc": "2.0", "id": 99, "method": "shutdown"}));
+    messages.push(json!({"jsonrpc": "2.0", "method": "exit"}));
+    let mut output = Vec::new();
+    serve_language_server(
+        Cursor::new(frame(&messages)),
+        &mut output,
+        environment.clone(),
+    )
+    .unwrap();
+    unframe(&String::from_utf8(output).unwrap())
+}
+
+fn request(id: u64, method: &str, line: u64, character: u64) -> Value {
+    json!({"jsonrpc": "2.0", "id": id, "method": method, "params": {
+        "textDocument": {"uri": URI},
+        "position": {"line": line, "character": character}
+    }})
+}
+
+fn response(messages: &[Value], id: u64) -> &Value {
+    let message = messages.iter().find(|m| m["id"] == id).unwrap();
+    &message["result"]
+}
+
+fn diagnostics(messages: &[Value]) -> &Vec<Value> {
+    let message = (messages.iter())
+        .find(|m| m["method"] == "textDocument/publishDiagnostics")
+        .unwrap();
+    assert_eq!(message["params"]["uri"], URI);
+    message["params"]["diagnostics"].as_array().unwrap()
+}
+
+#[test]
+fn advertises_capabilities() {
+    let messages = session("let x = 1", &[]);
+    let capabilities = &response(&messages, 0)["capabilities"];
+    assert_eq!(capabilities["hoverProvider"], true);
+    assert_eq!(capabilities["definitionProvider"], true);
+    assert_eq!(response(&messages, 99), &Value::Null);
+}
+
+#[test]
+fn well_typed_documents_have_no_diagnostics() {
+    let messages = session("let id = \\x -> x\nlet y = id 1", &[]);
+    assert!(diagnostics(&messages).is_empty());
+}
+
+#[test]
+fn unbound_names_are_reported_where_they_are_used() {
+    let messages = session("let x = 1\nlet y = x + nope", &[]);
+    let diagnostics = diagnostics(&messages);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(
+        diagnostics[0]["range"],
+        json!({"start": {"line": 1, "character": 12}, "end": {"line": 1, "character": 16}})
+    );
+    assert!(diagnostics[0]["message"].as_str().unwrap().contains("nope"));
+}
+
+#[test]
+fn parse_errors_are_reported() {
+    let messages = session("let x = (1", &[]);
+    let diagnostics = diagnostics(&messages);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0]["severity"], 1);
+}
+
+#[test]
+fn type_errors_are_reported_on_their_slice() {
+    let messages = session("let x = 1 + \"s\"", &[]);
+    let ranges = (diagnostics(&messages).iter())
+        .map(|diagnostic| {
+            let range = &diagnostic["range"];
+            (
+                range["start"]["character"].clone(),
+                range["end"]["character"].clone(),
+            )
+        })
+        .collect::<Vec<_>>();
+    // `+` and `"s"`, but not `1`
+    assert_eq!(ranges, [(json!(10), json!(11)), (json!(12), json!(15))]);
+}
+
+#[test]
+fn edits_are_rechecked() {
+    let change = json!({"jsonrpc": "2.0", "method": "textDocument/didChange", "params": {
+        "textDocument": {"uri": URI, "version": 2},
+        "contentChanges": [{"text": "let x = 1"}]
+    }});
+    let messages = session("let x = nope", &[change]);
+    let published = (messages.iter())
+        .filter(|m| m["method"] == "textDocument/publishDiagnostics")
+        .map(|m| m["params"]["diagnostics"].as_array().unwrap().len())
+        .collect::<Vec<_>>();
+    assert_eq!(published, [1, 0]);
+}
+
+#[test]
+fn hover_shows_types() {
+    let text = "let id = \\x -> x\nlet y = id 1";
+    let messages = session(
+        text,
+        &[
+            request(1, "textDocument/hover", 1, 8),
+            request(2, "textDocument/hover", 1, 11),
+        ],
+    );
+    let hover = response(&messages, 1);
+    assert_eq!(hover["contents"]["value"], "```\nid : Int -> Int\n```");
+    assert_eq!(
+        hover["range"],
+        json!({"start": {"line": 1, "character": 8}, "end": {"line": 1, "character": 10}})
+    );
+    assert_eq!(response(&messages, 2)["contents"]["value"], "```\nInt\n```");
+}
+
+#[test]
+fn statements_moved_by_an_edit_are_found_where_they_are_now() {
+    let change = json!({"jsonrpc": "2.0", "method": "textDocument/didChange", "params": {
+        "textDocument": {"uri": URI, "version": 2},
+        "contentChanges": [{"text": "let n = 1\n\nlet id = \\x -> x\nlet y = id n"}]
+    }});
+    let messages = session(
+        "let id = \\x -> x\nlet y = id 1",
+        &[
+            change,
+            request(1, "textDocument/hover", 3, 8),
+            request(2, "textDocument/definition", 3, 8),
+        ],
+    );
+    let hover = response(&messages, 1);
+    assert_eq!(hover["contents"]["value"], "```\nid : Int -> Int\n```");
+    assert_eq!(
+        hover["range"],
+        json!({"start": {"line": 3, "character": 8}, "end": {"line": 3, "character": 10}})
+    );
+    assert_eq!(
+        response(&messages, 2)["range"],
+        json!({"start": {"line": 2, "character": 4}, "end": {"line": 2, "character": 6}})
+    );
+}
+
+#[test]
+fn definition_finds_let_bound_names() {
+    let text = "let double = \\x -> x + x\nlet four = double 2\nlet f = \\x -> x";
+    let messages = session(
+        text,
+        &[
+            request(1, "textDocument/definition", 1, 13),
+            // a lambda's parameter isn't declared by a statement
+            request(2, "textDocument/definition", 2, 14),
+        ],
+    );
+    let definition = response(&messages, 1);
+    assert_eq!(definition["uri"], URI);
+    assert_eq!(
+        definition["range"],
+        json!({"start": {"line": 0, "character": 4}, "end": {"line": 0, "character": 10}})
+    );
+    assert_eq!(response(&messages, 2), &Value::Null);
+}
+
+#[test]
+fn unknown_requests_get_errors() {
+    let messages = session(
+        "let x = 1",
+        &[json!({"jsonrpc": "2.0", "id": 1, "method": "textDocument/rename", "params": {}})],
+    );
+    let message = messages.iter().find(|m| m["id"] == 1).unwrap();
+    assert_eq!(message["error"]["code"], -32601);
+}
+
+/// frame a body that needn't be valid JSON
+fn frame_raw(body: &[u8]) -> Vec<u8> {
+    let mut bytes = format!("Content-Length: {}\r\n\r\n", body.len()).into_bytes();
+    bytes.extend(body);
+    bytes
+}
+
+#[test]
+fn malformed_messages_get_parse_errors_and_the_server_carries_on() {
+    let mut input = frame_raw(b"{\"jsonrpc\": \"2.0\", \"id\": 1,");
+    input.extend(frame_raw(b"\xff\xfe"));
+    input.extend(frame(&[
+        json!({"jsonrpc": "2.0", "id": 2, "method": "shutdown"}),
+        json!({"jsonrpc": "2.0", "method": "exit"}),
+    ]));
+    let mut output = Vec::new();
+    serve_language_server(Cursor::new(input), &mut output, default_environment()).unwrap();
+    let messages = unframe(&String::from_utf8(output).unwrap());
+    assert_eq!(messages.len(), 3);
+    for message in &messages[..2] {
+        assert_eq!(message["id"], Value::Null);
+        assert_eq!(message["error"]["code"], -32700);
+    }
+    assert_eq!(response(&messages, 2), &Value::Null);
+}
+
+#[test]
+fn escaped_surrogate_pairs_are_decoded() {
+    // `let s = ["😀", nope]`, with the emoji escaped as UTF-16 surrogates
+    let open = br#"{"jsonrpc": "2.0", "method": "textDocument/didOpen", "params": {
+        "textDocument": {"uri": "file:///test.hhs", "text": "let s = [\"\ud83d\ude00\", nope]"}
+    }}"#;
+    let mut input = frame_raw(open);
+    input.extend(frame(&[json!({"jsonrpc": "2.0", "method": "exit"})]));
+    let mut output = Vec::new();
+    serve_language_server(Cursor::new(input), &mut output, default_environment()).unwrap();
+    let messages = unframe(&String::from_utf8(output).unwrap());
+    let diagnostics = diagnostics(&messages);
+    assert_eq!(diagnostics.len(), 1);
+    // the emoji is two UTF-16 code units, so `nope` starts at 15
+    assert_eq!(
+        diagnostics[0]["range"]["start"],
+        json!({"line": 0, "character": 15})
+    );
+}
+
+#[test]
+fn documents_are_checked_with_the_servers_prelude() {
+    let prelude = parse_environment("double : Int -> Int").unwrap();
+    let messages = session_with(&prelude, URI, "let n = double 1", &[]);
+    assert!(diagnostics(&messages).is_empty());
+    let messages = session_with(&prelude, URI, "let n = print 1", &[]);
+    assert_eq!(
+        diagnostics(&messages)[0]["message"],
+        "unrecognized identifier `print`"
+    );
+}
+
+/// a directory holding `files`, for a document to import them
+fn directory(files: &[(&str, &str)]) -> std::path::PathBuf {
+    let directory = std::env::temp_dir().join(format!("lsp_imports_{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    for (name, source) in files {
+        std::fs::write(directory.join(name), source).unwrap();
+    }
+    directory
+}
+
+#[test]
+fn imports_are_read_from_next_to_the_document() {
+    let directory = directory(&[("Lib.hhs", "let double = \\x -> x + x")]);
+    let uri = format!("file://{}/Main.hhs", directory.display());
+    let text = "import Lib (double)\nlet n = double 2\nlet m = Lib.double n";
+    let hover = json!({"jsonrpc": "2.0", "id": 1, "method": "textDocument/hover", "params": {
+        "textDocument": {"uri": uri},
+        "position": {"line": 1, "character": 8}
+    }});
+    let messages = session_with(&default_environment(), &uri, text, &[hover]);
+
+    let missing = session_with(
+        &default_environment(),
+        &uri,
+        "let n = 1\nimport Nowhere",
+        &[],
+    );
+    std::fs::remove_dir_all(&directory).unwrap();
+
+    let published = (messages.iter())
+        .find(|m| m["method"] == "textDocument/publishDiagnostics")
+        .unwrap();
+    assert_eq!(published["params"]["diagnostics"], json!([]));
+    assert_eq!(
+        response(&messages, 1)["contents"]["value"],
+        "```\ndouble : Int -> Int\n```"
+    );
+    let published = (missing.iter())
+        .find(|m| m["method"] == "textDocument/publishDiagnostics")
+        .unwrap();
+    let diagnostic = &published["params"]["diagnostics"][0];
+    assert_eq!(
+        diagnostic["range"],
+        json!({"start": {"line": 1, "character": 0}, "end": {"line": 1, "character": 14}})
+    );
+    assert!(
+        (diagnostic["message"].as_str().unwrap()).starts_with("can't read module `Nowhere`"),
+        "{diagnostic}"
+    );
+}
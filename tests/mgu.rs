@@ -0,0 +1,22 @@
+use heeren_hage_swierstra::{mgu, Type, TypeError};
+
+#[test]
+fn mismatched_aliases_are_named() {
+    let names = Type::Alias("Names", Type::list(Type::Str).into());
+    assert_eq!(
+        mgu(&names, &Type::Int).unwrap_err().to_string(),
+        "unable to unify types: Names and Int"
+    );
+    assert!(mgu(&names, &Type::list(Type::Str)).is_ok());
+}
+
+#[test]
+fn mismatched_aliases_with_the_same_name_are_expanded() {
+    // as when one shadows the other
+    let outer = Type::Alias("N", Type::Int.into());
+    let inner = Type::Alias("N", Type::Str.into());
+    assert_eq!(
+        mgu(&Type::list(outer), &Type::list(inner)),
+        Err(TypeError::Mismatch(Type::Int, Type::Str))
+    );
+}
@@ -0,0 +1,139 @@
+use std::{collections::HashMap, io};
+
+use heeren_hage_swierstra::{
+    default_environment, scheme_to_string, Module, ModuleError, ModuleLoader, TypeError,
+};
+
+/// a loader whose modules are the `(name, source)` pairs in `modules`
+fn loader(modules: &[(&str, &str)]) -> ModuleLoader {
+    let sources = (modules.iter())
+        .map(|(name, source)| ((*name).to_owned(), (*source).to_owned()))
+        .collect::<HashMap<_, _>>();
+    ModuleLoader::new(default_environment(), move |name| {
+        sources
+            .get(name)
+            .cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no such module"))
+    })
+}
+
+fn export(module: &Module, name: &str) -> String {
+    scheme_to_string(&module.exports[name])
+}
+
+const GEOMETRY: &str = "data Shape = Circle Int | Square Int
+let unit = Circle 1
+let double = \\x -> x + x";
+
+#[test]
+fn imports_are_in_scope_qualified_and_listed_names_unqualified() {
+    let mut loader = loader(&[("Geometry", GEOMETRY)]);
+    let source = "import Geometry (double)
+let a = double 2
+let b = Geometry.double 3
+let c = Geometry.unit";
+    let (main, _) = loader.check("Main", source).unwrap();
+    assert_eq!(export(&main, "a"), "Int");
+    assert_eq!(export(&main, "b"), "Int");
+    assert_eq!(export(&main, "c"), "Geometry.Shape");
+
+    let unqualified = loader.check("Main", "import Geometry\nlet u = unit");
+    assert!(matches!(
+        unqualified,
+        Err(ModuleError::Type {
+            error: TypeError::UnboundIdentifiers(_),
+            ..
+        })
+    ));
+}
+
+#[test]
+fn export_statements_limit_what_can_be_imported() {
+    let lib = "export double\nlet double = \\x -> x + x\nlet secret = 1";
+    let mut loader = loader(&[("Lib", lib), ("Bad", "export missing\nlet x = 1")]);
+    let exports = &loader.load("Lib").unwrap().exports;
+    assert_eq!(exports.keys().copied().collect::<Vec<_>>(), ["double"]);
+    assert_eq!(
+        loader.check("Main", "import Lib (secret)").unwrap_err(),
+        ModuleError::NotExported {
+            module: "Lib",
+            name: "secret"
+        }
+    );
+    assert_eq!(
+        loader.load("Bad").unwrap_err(),
+        ModuleError::UnknownExport {
+            module: "Bad",
+            name: "missing"
+        }
+    );
+}
+
+#[test]
+fn import_cycles_are_reported() {
+    let mut loader = loader(&[
+        ("A", "import B\nlet a = 1"),
+        ("B", "import C\nlet b = 1"),
+        ("C", "import A\nlet c = 1"),
+    ]);
+    assert_eq!(
+        loader.load("A").unwrap_err(),
+        ModuleError::Cycle(vec!["A", "B", "C", "A"])
+    );
+}
+
+#[test]
+fn missing_modules_are_reported() {
+    let mut loader = loader(&[]);
+    assert!(matches!(
+        loader.check("Main", "import Nowhere\nlet x = 1"),
+        Err(ModuleError::Read {
+            module: "Nowhere",
+            ..
+        })
+    ));
+}
+
+#[test]
+fn types_declared_by_different_modules_are_different() {
+    let mut loader = loader(&[
+        ("A", "data T = MkA\nlet a = MkA"),
+        ("B", "data T = MkB\nlet b = MkB"),
+    ]);
+    let (main, _) = loader
+        .check("Main", "import A (a)\nimport B (b)\nlet both = [a, a]")
+        .unwrap();
+    assert_eq!(export(&main, "both"), "List A.T");
+    assert!(matches!(
+        loader.check("Main", "import A (a)\nimport B (b)\nlet both = [a, b]"),
+        Err(ModuleError::Type {
+            error: TypeError::Mismatch(..),
+            ..
+        })
+    ));
+}
+
+#[test]
+fn imports_inside_blocks_are_rejected() {
+    let mut loader = loader(&[("Geometry", GEOMETRY)]);
+    assert_eq!(
+        loader
+            .check("Main", "let x = do { import Geometry; 1 }")
+            .unwrap_err(),
+        ModuleError::Type {
+            module: "Main",
+            error: TypeError::NotAtTopLevel("import")
+        }
+    );
+}
+
+#[test]
+fn names_local_to_a_trailing_expression_are_not_exported() {
+    let mut loader = loader(&[
+        ("LetIn", "let n = 1\nlet x = 1 in x + n"),
+        ("Block", "do { let a = 1; a + 1 }"),
+    ]);
+    let exports = &loader.load("LetIn").unwrap().exports;
+    assert_eq!(exports.keys().collect::<Vec<_>>(), [&"n"]);
+    assert!(loader.load("Block").unwrap().exports.is_empty());
+}
@@ -0,0 +1,93 @@
+use std::process::Command;
+
+use heeren_hage_swierstra::{
+    default_environment, environment_to_string, parse_environment, scheme_to_string, Session,
+    SessionError, TypeError,
+};
+
+#[test]
+fn the_built_in_prelude_is_the_same_every_time() {
+    let environment = default_environment();
+    assert_eq!(
+        scheme_to_string(&environment["map"]),
+        "forall a b. (a -> b) -> List a -> List b"
+    );
+    assert_eq!(
+        environment_to_string(&default_environment()),
+        environment_to_string(&environment)
+    );
+}
+
+#[test]
+fn programs_can_use_a_custom_prelude() {
+    let prelude = parse_environment(
+        "-- just what's needed\ndouble : Int -> Int\nhead : forall a. List a -> a; pair : forall a b. a -> b -> Pair a b",
+    )
+    .unwrap();
+    let mut session = Session::with_environment(prelude);
+    assert_eq!(session.run("let n = double (head [1])").unwrap(), "n : Int");
+    assert_eq!(
+        session.run("let p = \\x -> pair x \"s\"").unwrap(),
+        "p : forall a. a -> Pair a Str"
+    );
+    assert!(matches!(
+        session.run("print 1"),
+        Err(SessionError::Type(TypeError::UnboundIdentifiers(_)))
+    ));
+}
+
+#[test]
+fn variables_starting_with_an_underscore_are_shared() {
+    let environment = parse_environment("get : Ref _a -> _a\nset : _a -> Nothing").unwrap();
+    let mut session = Session::with_environment(environment);
+    session.run("let x = set 1").unwrap();
+    assert!(session.run("let y = set \"s\"").is_err());
+}
+
+#[test]
+fn prelude_errors_are_located() {
+    let error = parse_environment("len : Str -> Int\nid : a -> a").unwrap_err();
+    assert_eq!(error.message, "type variable `a` isn't quantified");
+    assert_eq!(error.span.line_col("len : Str -> Int\nid : a -> a"), (2, 1));
+    let error = parse_environment("len Str -> Int").unwrap_err();
+    assert_eq!(error.span.line_col("len Str -> Int"), (1, 5));
+}
+
+/// run the checker with `prelude` as the prelude on a file containing
+/// `source`, returning whether it succeeded and what it printed
+fn check(prelude: &str, source: &str) -> (bool, String, String) {
+    let directory = std::env::temp_dir();
+    let id = std::process::id();
+    let prelude_path = directory.join(format!("prelude_{id}.hhsi"));
+    let source_path = directory.join(format!("prelude_{id}.hhs"));
+    std::fs::write(&prelude_path, prelude).unwrap();
+    std::fs::write(&source_path, source).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_heeren_hage_swierstra"))
+        .arg("--prelude")
+        .arg(&prelude_path)
+        .arg(&source_path)
+        .output()
+        .unwrap();
+    std::fs::remove_file(&prelude_path).unwrap();
+    std::fs::remove_file(&source_path).unwrap();
+    (
+        output.status.success(),
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+#[test]
+fn the_checker_reads_a_prelude_file() {
+    let (success, stdout, _) = check("double : Int -> Int", "let n = double 2");
+    assert!(success);
+    assert_eq!(stdout, "n : Int\n");
+
+    let (success, stdout, stderr) = check("double : a -> a", "let n = double 2");
+    assert!(!success);
+    assert_eq!(stdout, "");
+    assert!(
+        stderr.ends_with(".hhsi:1:1: error: type variable `a` isn't quantified\n"),
+        "{stderr}"
+    );
+}
@@ -0,0 +1,338 @@
+//! Property tests over random programs that are well-typed by construction.
+//!
+//! Programs are generated top-down from the type they must have. Every
+//! lambda's parameter is pinned to the type it was generated for, by an
+//! annotation or by sharing a list with a value of that type, so the type a
+//! program was generated for is its principal type, up to the names of type
+//! variables.
+
+use std::panic::catch_unwind;
+
+use heeren_hage_swierstra::{
+    algorithm_w, default_environment, infer_type, intern, scheme_to_string, FreeVars, Ir, Name,
+    Type,
+};
+
+const PROGRAMS: u64 = 500;
+const DEPTH: u32 = 4;
+
+/// xorshift, so each seed gives the same program on every platform
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self(seed.wrapping_add(1).wrapping_mul(0x9e37_79b9_7f4a_7c15))
+    }
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+    fn chance(&mut self, percent: u64) -> bool {
+        self.next() % 100 < percent
+    }
+}
+
+/// polymorphic functions a program can bind with `let` and use at any type
+#[derive(Clone, Copy)]
+enum Combinator {
+    /// `\x -> x`
+    Id,
+    /// `\x y -> x`
+    Const,
+    /// `\f x -> f (f x)`
+    Twice,
+}
+
+struct Generator {
+    rng: Rng,
+    names: usize,
+    /// monomorphic bindings in scope, innermost last
+    locals: Vec<(Name, Type)>,
+    combinators: Vec<(Name, Combinator)>,
+    /// type variables used so far
+    vars: u32,
+}
+
+impl Generator {
+    fn new(seed: u64) -> Self {
+        Self {
+            rng: Rng::new(seed),
+            names: 0,
+            locals: Vec::new(),
+            combinators: Vec::new(),
+            vars: 0,
+        }
+    }
+
+    /// a program, and its principal type: a function of up to three
+    /// parameters, each of which is a ground type or a type variable
+    fn program(&mut self) -> (Ir, Type) {
+        let mut params = Vec::new();
+        for _ in 0..self.rng.below(4) {
+            if self.rng.chance(50) {
+                params.push(Type::Unknown(self.vars));
+                self.vars += 1;
+            } else {
+                params.push(self.ground_type());
+            }
+        }
+        let vars = (params.iter())
+            .filter(|param| matches!(param, Type::Unknown(_)))
+            .collect::<Vec<_>>();
+        let result = if !vars.is_empty() && self.rng.chance(50) {
+            vars[self.rng.below(vars.len())].clone()
+        } else {
+            self.ground_type()
+        };
+        let ty = (params.into_iter().rev())
+            .fold(result, |result, param| Type::F(param.into(), result.into()));
+        (self.expr(&ty, DEPTH), ty)
+    }
+
+    /// an expression whose principal type is `ty`, given the bindings in
+    /// scope
+    fn expr(&mut self, ty: &Type, depth: u32) -> Ir {
+        if depth > 0 {
+            match self.rng.below(8) {
+                0 => return self.let_(ty, depth - 1),
+                1 => return self.let_combinator(ty, depth - 1),
+                2 => {
+                    let discarded = self.any_type();
+                    return Ir::Seq(
+                        self.expr(&discarded, depth - 1).into(),
+                        self.expr(ty, depth - 1).into(),
+                    );
+                }
+                3 => {
+                    let param = Some(self.any_type())
+                        .filter(is_pinnable)
+                        .unwrap_or(Type::Int);
+                    let f = Type::F(param.clone().into(), ty.clone().into());
+                    return app(self.expr(&f, depth - 1), self.expr(&param, depth - 1));
+                }
+                4 => {
+                    if let Some(ir) = self.use_combinator(ty, depth - 1) {
+                        return ir;
+                    }
+                }
+                _ => (),
+            }
+        }
+        let local = (self.locals.iter().rev())
+            .find(|(_, local)| local == ty)
+            .map(|(name, _)| *name);
+        if let Some(name) = local.filter(|_| self.rng.chance(50)) {
+            return Ir::Id(name);
+        }
+        let below = depth.saturating_sub(1);
+        match ty {
+            Type::Int if depth > 0 && self.rng.chance(50) => match self.rng.below(3) {
+                0 => Ir::Add(
+                    self.expr(&Type::Int, below).into(),
+                    self.expr(&Type::Int, below).into(),
+                ),
+                1 => app(Ir::Id("len"), self.expr(&Type::Str, below)),
+                _ => {
+                    let element = self.any_type();
+                    app(Ir::Id("length"), self.expr(&Type::list(element), below))
+                }
+            },
+            Type::Int => Ir::LiteralInt(self.rng.below(100) as i64),
+            Type::Str => Ir::LiteralStr(intern(&format!("s{}", self.rng.below(100)))),
+            Type::Nothing if depth > 0 && self.rng.chance(50) => {
+                let printed = self.any_type();
+                app(Ir::Id("print"), self.expr(&printed, below))
+            }
+            Type::Nothing => Ir::Nop,
+            // never empty, since `[]` is more general than any list type
+            Type::App(_, element) => {
+                let elements = (0..=self.rng.below(3))
+                    .map(|_| self.expr(element, below))
+                    .collect();
+                Ir::List(elements)
+            }
+            Type::F(param, result) => self.lambda(param, result, depth),
+            Type::Unknown(_) => Ir::Id(local.expect("type variables only come from bindings")),
+            _ => unreachable!("no expressions of type {ty}"),
+        }
+    }
+
+    /// `\x -> body`, where `body` starts by pinning `x` to `param`
+    fn lambda(&mut self, param: &Type, result: &Type, depth: u32) -> Ir {
+        let binding = self.fresh_name("x");
+        let witness = (self.locals.iter())
+            .find(|(_, local)| local == param)
+            .map(|(name, _)| *name);
+        let pin = if param.free_vars().is_empty() {
+            Some(Ir::Annot {
+                expr: Ir::Id(binding).into(),
+                ty: param.clone(),
+            })
+        } else {
+            // the first binding of a type variable doesn't need pinning
+            witness.map(|witness| Ir::List(vec![Ir::Id(binding), Ir::Id(witness)]))
+        };
+        self.locals.push((binding, param.clone()));
+        let body = self.expr(result, depth);
+        self.locals.pop();
+        let body = match pin {
+            Some(pin) => Ir::Seq(pin.into(), body.into()),
+            None => body,
+        };
+        Ir::Lam {
+            binding,
+            body: body.into(),
+        }
+    }
+
+    /// `let v = e1 in e2`, with `v` monomorphic in `e2`
+    fn let_(&mut self, ty: &Type, depth: u32) -> Ir {
+        let bound = self.any_type();
+        let e1 = self.expr(&bound, depth);
+        let binding = self.fresh_name("v");
+        self.locals.push((binding, bound));
+        let e2 = self.expr(ty, depth);
+        self.locals.pop();
+        Ir::Let {
+            e1: e1.into(),
+            binding,
+            e2: e2.into(),
+        }
+    }
+
+    /// `let f = combinator in e2`, with `f` polymorphic in `e2`
+    fn let_combinator(&mut self, ty: &Type, depth: u32) -> Ir {
+        let combinator = [Combinator::Id, Combinator::Const, Combinator::Twice][self.rng.below(3)];
+        let (x, y) = (self.fresh_name("p"), self.fresh_name("p"));
+        let e1 = match combinator {
+            Combinator::Id => lam(x, Ir::Id(x)),
+            Combinator::Const => lam(x, lam(y, Ir::Id(x))),
+            Combinator::Twice => lam(x, lam(y, app(Ir::Id(x), app(Ir::Id(x), Ir::Id(y))))),
+        };
+        let binding = self.fresh_name("f");
+        self.combinators.push((binding, combinator));
+        let e2 = self.expr(ty, depth);
+        self.combinators.pop();
+        Ir::Let {
+            e1: e1.into(),
+            binding,
+            e2: e2.into(),
+        }
+    }
+
+    /// a combinator in scope, applied to make a value of type `ty`
+    fn use_combinator(&mut self, ty: &Type, depth: u32) -> Option<Ir> {
+        if self.combinators.is_empty() {
+            return None;
+        }
+        let (name, combinator) = self.combinators[self.rng.below(self.combinators.len())];
+        Some(match combinator {
+            Combinator::Id => app(Ir::Id(name), self.expr(ty, depth)),
+            Combinator::Const => {
+                let ignored = self.any_type();
+                let x = self.expr(ty, depth);
+                app(app(Ir::Id(name), x), self.expr(&ignored, depth))
+            }
+            // `f`'s parameter has to be pinned, like any lambda's
+            Combinator::Twice if is_pinnable(ty) => {
+                let f = self.expr(&Type::F(ty.clone().into(), ty.clone().into()), depth);
+                app(app(Ir::Id(name), f), self.expr(ty, depth))
+            }
+            Combinator::Twice => return None,
+        })
+    }
+
+    fn ground_type(&mut self) -> Type {
+        match self.rng.below(5) {
+            0 => Type::Int,
+            1 => Type::Str,
+            2 => Type::Nothing,
+            3 => Type::list(Type::Int),
+            _ => Type::F(Type::Str.into(), Type::Int.into()),
+        }
+    }
+
+    /// a ground type, or a type variable some binding in scope has
+    fn any_type(&mut self) -> Type {
+        let vars = (self.locals.iter())
+            .filter(|(_, ty)| matches!(ty, Type::Unknown(_)))
+            .map(|(_, ty)| ty.clone())
+            .collect::<Vec<_>>();
+        if vars.is_empty() || self.rng.chance(60) {
+            return self.ground_type();
+        }
+        let var = vars[self.rng.below(vars.len())].clone();
+        match self.rng.below(3) {
+            0 => Type::list(var),
+            1 => Type::F(var.clone().into(), var.into()),
+            _ => var,
+        }
+    }
+
+    fn fresh_name(&mut self, prefix: &str) -> Name {
+        self.names += 1;
+        intern(&format!("{prefix}{}", self.names))
+    }
+}
+
+/// whether a lambda parameter of type `ty` can be pinned
+fn is_pinnable(ty: &Type) -> bool {
+    matches!(ty, Type::Unknown(_)) || ty.free_vars().is_empty()
+}
+
+fn app(e1: Ir, e2: Ir) -> Ir {
+    Ir::App {
+        e1: e1.into(),
+        e2: e2.into(),
+    }
+}
+
+fn lam(binding: Name, body: Ir) -> Ir {
+    Ir::Lam {
+        binding,
+        body: body.into(),
+    }
+}
+
+/// `ty` written the same way as any type that differs from it only in the
+/// names of its type variables
+fn canonical(ty: &Type) -> String {
+    scheme_to_string(&(ty.free_vars(), ty.clone()))
+}
+
+#[test]
+fn infer_type_finds_principal_types_of_random_programs() {
+    let environment = default_environment();
+    for seed in 0..PROGRAMS {
+        let (program, expected) = Generator::new(seed).program();
+        let expected = canonical(&expected);
+        let inferred = catch_unwind(|| infer_type(&environment, program.clone()))
+            .unwrap_or_else(|_| panic!("seed {seed}: infer_type panics on {program:?}"))
+            .unwrap_or_else(|e| panic!("seed {seed}: infer_type rejects {program:?}: {e}"));
+        assert_eq!(
+            canonical(inferred.1.ty()),
+            expected,
+            "seed {seed}: {program:?}"
+        );
+    }
+}
+
+#[test]
+fn algorithm_w_finds_principal_types_of_random_programs() {
+    let environment = default_environment();
+    for seed in 0..PROGRAMS {
+        let (program, expected) = Generator::new(seed).program();
+        let (_, ty) = algorithm_w(&environment, &program)
+            .unwrap_or_else(|e| panic!("seed {seed}: algorithm W rejects {program:?}: {e}"));
+        assert_eq!(
+            canonical(&ty),
+            canonical(&expected),
+            "seed {seed}: {program:?}"
+        );
+    }
+}
@@ -0,0 +1,102 @@
+#![cfg(feature = "serde")]
+
+use heeren_hage_swierstra::{
+    default_environment, deserialize_environment, infer_type, parse, serialize_environment, Ast,
+    Environment, Ir, Type, TypedIr,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+const PROGRAM: &str = r#"
+fn compose f g x = f (g x)
+let xs = map (\x -> x + 1) [1, 2, 3]
+data Pair a b = MkPair a b
+type Name = Str
+let greet = \n -> (n : Name)
+fn sum2 l = match l {
+  a :: b :: _ -> a + b
+  _ -> 0
+}
+let r = ref "escaped \"quotes\""
+r := !r
+print (compose length (\s -> [s]) (MkPair 1 "one"))
+"#;
+
+fn json_round_trip<T: Serialize + DeserializeOwned>(value: &T) -> T {
+    let json = serde_json::to_string(value).unwrap();
+    serde_json::from_str(&json).unwrap()
+}
+
+fn binary_round_trip<T: Serialize + DeserializeOwned>(value: &T) -> T {
+    let bytes = rmp_serde::to_vec(value).unwrap();
+    rmp_serde::from_slice(&bytes).unwrap()
+}
+
+/// `Ast`, `Ir` and `TypedIr` don't implement `PartialEq`, so compare how
+/// they serialize, which is also a check that serializing is deterministic
+fn assert_same<T: Serialize>(original: &T, round_tripped: &T) {
+    assert_eq!(
+        serde_json::to_string(original).unwrap(),
+        serde_json::to_string(round_tripped).unwrap()
+    );
+}
+
+fn typed() -> TypedIr {
+    let ir = parse(PROGRAM).unwrap().desugar().unwrap();
+    infer_type(&default_environment(), ir).unwrap().1
+}
+
+#[test]
+fn trees_round_trip() {
+    let ast = parse(PROGRAM).unwrap();
+    assert_same(&ast, &json_round_trip::<Ast>(&ast));
+    assert_same(&ast, &binary_round_trip::<Ast>(&ast));
+
+    let ir = ast.desugar().unwrap();
+    assert_same(&ir, &json_round_trip::<Ir>(&ir));
+    assert_same(&ir, &binary_round_trip::<Ir>(&ir));
+
+    let typed = typed();
+    assert_same(&typed, &json_round_trip::<TypedIr>(&typed));
+    assert_same(&typed, &binary_round_trip::<TypedIr>(&typed));
+}
+
+#[test]
+fn types_round_trip() {
+    for (_, (_, ty)) in default_environment() {
+        assert_eq!(ty, json_round_trip(&ty));
+        assert_eq!(ty, binary_round_trip(&ty));
+    }
+    let alias = Type::Alias("Names", Type::list(Type::Str).into());
+    assert_eq!(alias, json_round_trip(&alias));
+}
+
+#[test]
+fn type_json_is_stable() {
+    let ty = Type::F(Type::list(Type::Unknown(3)).into(), Type::Int.into());
+    assert_eq!(
+        serde_json::to_string(&ty).unwrap(),
+        r#"{"F":[{"App":[{"Con":"List"},{"Unknown":3}]},"Int"]}"#
+    );
+}
+
+#[test]
+fn environment_round_trips() {
+    let environment = default_environment();
+
+    let mut json = Vec::new();
+    serialize_environment(&environment, &mut serde_json::Serializer::new(&mut json)).unwrap();
+    let from_json: Environment =
+        deserialize_environment(&mut serde_json::Deserializer::from_slice(&json)).unwrap();
+    assert_eq!(environment, from_json);
+
+    // schemes and names are written in a fixed order
+    let mut again = Vec::new();
+    serialize_environment(&from_json, &mut serde_json::Serializer::new(&mut again)).unwrap();
+    assert_eq!(json, again);
+
+    let mut bytes = Vec::new();
+    serialize_environment(&environment, &mut rmp_serde::Serializer::new(&mut bytes)).unwrap();
+    let from_bytes =
+        deserialize_environment(&mut rmp_serde::Deserializer::new(&bytes[..])).unwrap();
+    assert_eq!(environment, from_bytes);
+}
@@ -0,0 +1,96 @@
+use heeren_hage_swierstra::{Session, SessionError, TypeError};
+
+#[test]
+fn bindings_stay_in_scope_for_later_inputs() {
+    let mut session = Session::new();
+    assert_eq!(
+        session.run("let id = \\x -> x").unwrap(),
+        "id : forall a. a -> a"
+    );
+    assert_eq!(session.run("let n = id 1").unwrap(), "n : Int");
+    assert_eq!(session.run("id \"s\"").unwrap(), "Str");
+    assert_eq!(session.run(":type id n").unwrap(), "id n : Int");
+}
+
+#[test]
+fn failed_inputs_leave_the_session_unchanged() {
+    let mut session = Session::new();
+    session.run("let n = 1").unwrap();
+    assert!(matches!(
+        session.run("let m = n + \"s\""),
+        Err(SessionError::Type(TypeError::Mismatch(..)))
+    ));
+    assert!(matches!(
+        session.run("let m = ("),
+        Err(SessionError::Parse(_))
+    ));
+    assert!(matches!(
+        session.run(":nope"),
+        Err(SessionError::UnknownCommand(_))
+    ));
+    assert!(!session.environment().contains_key("m"));
+}
+
+#[test]
+fn reset_forgets_bindings() {
+    let mut session = Session::new();
+    session.run("let n = 1").unwrap();
+    session.run(":reset").unwrap();
+    assert!(matches!(
+        session.run("n"),
+        Err(SessionError::Type(TypeError::UnboundIdentifiers(_)))
+    ));
+}
+
+#[test]
+fn a_ref_is_fixed_to_the_first_type_stored_in_it() {
+    let mut session = Session::new();
+    let declared = session.run("let r = ref []").unwrap();
+    assert!(declared.starts_with("r : Ref (List t"), "{declared}");
+    assert!(!declared.contains("forall"), "{declared}");
+    assert_eq!(session.run(":type r").unwrap(), declared);
+
+    session.run("r := [1]").unwrap();
+    assert_eq!(session.run(":type r").unwrap(), "r : Ref (List Int)");
+    assert!(matches!(
+        session.run("r := [\"s\"]"),
+        Err(SessionError::Type(TypeError::Mismatch(..)))
+    ));
+}
+
+#[test]
+fn functions_using_a_weak_ref_are_not_generalized() {
+    let mut session = Session::new();
+    session.run("let r = ref []").unwrap();
+    let f = session.run("let f = \\x -> do { r := [x]; x }").unwrap();
+    assert!(!f.contains("forall"), "{f}");
+    session.run("let a = f 1").unwrap();
+    assert!(session.run("let b = f \"s\"").is_err());
+    assert_eq!(session.run(":type r").unwrap(), "r : Ref (List Int)");
+}
+
+#[test]
+fn names_local_to_an_expression_stay_out_of_scope() {
+    let mut session = Session::new();
+    assert_eq!(session.run("do { let a = 1; a + 1 }").unwrap(), "Int");
+    assert_eq!(session.run("let k = 1 in k").unwrap(), "Int");
+    assert!(session.run(":type a").is_err());
+    assert!(session.run(":type k").is_err());
+    assert_eq!(session.run("let n = 1; n + 1").unwrap(), "n : Int\nInt");
+}
+
+#[test]
+fn types_with_values_in_scope_cannot_be_declared_again() {
+    let mut session = Session::new();
+    session.run("data T = A; let a = A").unwrap();
+    assert_eq!(
+        session.run("data T = B Int; let l = [a, B 1]"),
+        Err(SessionError::Type(TypeError::DuplicateType("T")))
+    );
+    assert!(!session.environment().contains_key("l"));
+    session.run(":reset").unwrap();
+    assert_eq!(
+        session.run("data T = B Int; B").unwrap(),
+        "B : Int -> T\nInt -> T"
+    );
+}
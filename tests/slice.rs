@@ -0,0 +1,37 @@
+use heeren_hage_swierstra::{
+    default_environment, generate_constraints, parse_expr, type_error_slice, Span,
+};
+
+/// the text of each piece of the slice of `source`
+fn slice(source: &str) -> Option<Vec<&str>> {
+    let ir = parse_expr(source).unwrap().desugar().unwrap();
+    let slice = type_error_slice(&default_environment(), ir)?;
+    Some(
+        (slice.into_iter())
+            .map(|Span { start, end }| source[start..end].trim())
+            .filter(|text| !text.is_empty())
+            .collect(),
+    )
+}
+
+#[test]
+fn well_typed_programs_have_no_slice() {
+    assert_eq!(slice("let f = \\x -> x + 1 in f 2"), None);
+    assert_eq!(slice("nonexistent 1"), None);
+}
+
+#[test]
+fn literals_are_blamed_along_with_what_they_disagree_with() {
+    assert_eq!(slice("1 + \"s\""), Some(vec!["+", "\"s\""]));
+    assert_eq!(
+        slice("let f = \\x -> x + 1 in f \"s\""),
+        Some(vec!["\\x ->", "x", "+", "f", "\"s\""])
+    );
+}
+
+#[test]
+fn slicing_leaves_constraint_generation_alone() {
+    let ir = parse_expr("1 + \"s\"").unwrap().desugar().unwrap();
+    let (constraints, _) = generate_constraints(&default_environment(), ir).unwrap();
+    assert_eq!(constraints.iter().count(), 2);
+}
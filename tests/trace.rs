@@ -0,0 +1,83 @@
+use heeren_hage_swierstra::{
+    default_environment, generate_constraints, parse_expr, solve_traced, SolveEvent, TypeError,
+};
+use serde_json::Value;
+
+/// the events of solving the constraints of `source`, and the outcome
+fn trace(source: &str) -> (Vec<SolveEvent>, Result<(), TypeError>) {
+    let ir = parse_expr(source).unwrap().desugar().unwrap();
+    let (constraints, _) = generate_constraints(&default_environment(), ir).unwrap();
+    let mut events = Vec::new();
+    let solved = solve_traced(constraints, &mut |event| events.push(event));
+    (events, solved.map(|_| ()))
+}
+
+fn kind(event: &SolveEvent) -> &'static str {
+    match event {
+        SolveEvent::Unify { .. } => "unify",
+        SolveEvent::Instantiate { .. } => "instantiate",
+        SolveEvent::Generalize { .. } => "generalize",
+        SolveEvent::Fail { .. } => "fail",
+    }
+}
+
+#[test]
+fn a_let_is_generalized_before_its_uses_are_instantiated() {
+    let (events, solved) = trace("let id = \\x -> x in id 1");
+    assert!(solved.is_ok());
+    let kinds = events.iter().map(kind).collect::<Vec<_>>();
+    let generalized = kinds.iter().position(|kind| *kind == "generalize").unwrap();
+    let instantiated = kinds
+        .iter()
+        .position(|kind| *kind == "instantiate")
+        .unwrap();
+    assert!(generalized < instantiated, "{kinds:?}");
+    // the instance's equality is unified after it's made
+    assert_eq!(kinds.last(), Some(&"unify"), "{kinds:?}");
+    assert!(!kinds.contains(&"fail"));
+    assert!(events[0].to_string().starts_with("unify "));
+}
+
+#[test]
+fn solving_stops_at_the_first_failure() {
+    let (events, solved) = trace("len 1");
+    assert!(matches!(solved, Err(TypeError::Mismatch(..))));
+    let last = events.last().unwrap();
+    assert_eq!(kind(last), "fail");
+    let text = last.to_string();
+    assert!(text.starts_with("fail "), "{text}");
+    assert!(
+        text.ends_with("unable to unify types: Int and Str")
+            || text.ends_with("unable to unify types: Str and Int"),
+        "{text}"
+    );
+}
+
+#[test]
+fn events_are_written_as_json_objects() {
+    let (events, _) = trace("let id = \\x -> x in len (id 1)");
+    for event in &events {
+        let json = event.to_json();
+        assert!(!json.contains('\n'), "{json}");
+        let value: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["event"], kind(event));
+        assert!(value["constraint"].is_string(), "{json}");
+        let fields: &[&str] = match event {
+            SolveEvent::Unify { .. } => &["mgu", "substitution"],
+            SolveEvent::Instantiate { .. } | SolveEvent::Generalize { .. } => &["result"],
+            SolveEvent::Fail { .. } => &["error"],
+        };
+        for field in fields {
+            assert!(!value[field].is_null(), "{json} has no `{field}`");
+        }
+        if let SolveEvent::Unify { substitution, .. } = event {
+            let written = value["substitution"].as_object().unwrap();
+            assert_eq!(written.len(), substitution.len());
+            assert!(written.values().all(Value::is_string));
+        }
+    }
+    let kinds = events.iter().map(kind).collect::<Vec<_>>();
+    for expected in ["unify", "instantiate", "generalize", "fail"] {
+        assert!(kinds.contains(&expected), "no {expected} in {kinds:?}");
+    }
+}
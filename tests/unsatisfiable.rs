@@ -0,0 +1,67 @@
+use heeren_hage_swierstra::{
+    default_environment, fresh_type_id, generate_constraints, minimal_unsatisfiable_subset,
+    parse_expr, solve, Constraint, Constraints, Span, Type, TypeError,
+};
+
+fn constraints(source: &str) -> Constraints {
+    let ir = parse_expr(source).unwrap().desugar().unwrap();
+    generate_constraints(&default_environment(), ir).unwrap().0
+}
+
+/// whether `solve` rejects `constraints` with a contradiction
+fn contradictory(constraints: &[Constraint]) -> bool {
+    match solve(constraints.iter().cloned().collect()) {
+        Ok(_) | Err(TypeError::CircularGeneralization) => false,
+        Err(_) => true,
+    }
+}
+
+#[test]
+fn satisfiable_constraints_have_no_unsatisfiable_subset() {
+    assert!(minimal_unsatisfiable_subset(&constraints("let f = \\x -> x + 1 in f 2")).is_none());
+    assert!(minimal_unsatisfiable_subset(&Constraints::new()).is_none());
+}
+
+#[test]
+fn the_subset_is_unsatisfiable_and_each_member_is_needed() {
+    let all = constraints("let n = len \"a\" + 2 in let f = \\x -> x in f n + \"s\"");
+    let subset = minimal_unsatisfiable_subset(&all)
+        .unwrap()
+        .into_iter()
+        .map(|(constraint, _)| constraint)
+        .collect::<Vec<_>>();
+    assert!(subset.len() < all.iter().count());
+    assert!(contradictory(&subset));
+    for i in 0..subset.len() {
+        let mut rest = subset.clone();
+        rest.remove(i);
+        assert!(!contradictory(&rest), "{} isn't needed", subset[i]);
+    }
+}
+
+#[test]
+fn unrelated_constraints_are_left_out() {
+    let (t1, t2) = (
+        Type::Unknown(fresh_type_id()),
+        Type::Unknown(fresh_type_id()),
+    );
+    let mut constraints = Constraints::new();
+    constraints.insert_eq(Type::Int, t1.clone());
+    constraints.insert_eq(t1.clone(), Type::Str);
+    constraints.insert_eq(t2, Type::Int);
+    let subset = minimal_unsatisfiable_subset(&constraints).unwrap();
+    assert_eq!(
+        subset,
+        [
+            (Constraint::Equality(Type::Int, t1.clone()), None),
+            (Constraint::Equality(t1, Type::Str), None),
+        ]
+    );
+}
+
+#[test]
+fn members_keep_their_origins() {
+    let subset = minimal_unsatisfiable_subset(&constraints("1 + \"s\"")).unwrap();
+    let origins = subset.iter().map(|(_, origin)| *origin).collect::<Vec<_>>();
+    assert_eq!(origins, [Some(Span::new(0, 7))]);
+}